@@ -1,11 +1,37 @@
 use std::ops::Mul;
 
 use num::Complex;
+use wasm_bindgen::prelude::*;
 
 pub fn evaluate_polynomial(summands: &[(i32, i32)], q: &Complex<f64>)  -> Complex<f64> {
     summands.iter().map(|(exp, coef)| Complex::new(*coef as f64, 0.0) * q.powi(*exp)).sum()
 }
 
+// Ways of measuring how far a matrix is from the identity. The Frobenius
+// distance is dominated by the largest entries, which grow exponentially with
+// the length of the word when |q| > 1, so the others trade exactness for a
+// signal that stays informative for long words.
+#[repr(u8)]
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Metric {
+    // The Frobenius norm of A − I.
+    Frobenius,
+    // The operator norm of A − I, its largest singular value.
+    Operator,
+    // The logarithm of one plus the largest modulus of an entry of A − I.
+    LogMaxEntry,
+    // The logarithm of the spectral radius of A, which is non-negative as the
+    // matrices have determinant 1. It is not a distance from the identity: it
+    // vanishes whenever all eigenvalues lie on the unit circle, as they often
+    // do when |q| = 1, so it only measures growth and is not offered to
+    // players.
+    LogSpectralRadius,
+    // The Frobenius distance to the closest scalar matrix relative to the
+    // Frobenius norm of A, which does not change when A is rescaled.
+    Projective
+}
+
 #[derive(Debug)]
 pub struct Matrix {
     pub d: [[Complex<f64>; 3]; 3]
@@ -27,14 +53,7 @@ impl Mul for &Matrix {
 
 impl PartialEq for Matrix {
     fn eq(&self, rhs: &Matrix) -> bool {
-        for i in 0..3 {
-            for j in 0..3 {
-                if (self.d[i][j] - rhs.d[i][j]).norm() > 1e-10 {
-                    return false
-                }
-            }
-        }
-        true
+        self.approx_eq(rhs, 1e-10)
     }
 }
 
@@ -52,6 +71,19 @@ impl Matrix {
         res
     }
 
+    pub fn approx_eq(&self, rhs: &Matrix, tolerance: f64) -> bool {
+        for i in 0..3 {
+            for j in 0..3 {
+                // Entries that have overflowed are never close to anything.
+                let difference = (self.d[i][j] - rhs.d[i][j]).norm();
+                if !difference.is_finite() || difference > tolerance {
+                    return false
+                }
+            }
+        }
+        true
+    }
+
     pub fn distance_from_identity(&self) -> f64 {
         let mut norm_square = 0.0;
         for i in 0..3 {
@@ -63,6 +95,176 @@ impl Matrix {
         norm_square.sqrt()
     }
 
+    pub fn distance(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::Frobenius => self.distance_from_identity(),
+            Metric::Operator => self.minus_identity().operator_norm(),
+            Metric::LogMaxEntry => self.minus_identity().max_entry().ln_1p(),
+            Metric::LogSpectralRadius => self.log_spectral_radius().max(0.0),
+            Metric::Projective => {
+                let norm = self.frobenius_norm();
+                if norm == 0.0 { 0.0 } else { self.distance_from_scalar() / norm }
+            }
+        }
+    }
+
+    fn minus_identity(&self) -> Matrix {
+        let mut res = Matrix { d: self.d };
+        for i in 0..3 {
+            res.d[i][i] -= 1.0;
+        }
+        res
+    }
+
+    pub fn scale(&self, c: Complex<f64>) -> Matrix {
+        Matrix { d: self.d.map(|row| row.map(|z| z * c)) }
+    }
+
+    pub fn conjugate_transpose(&self) -> Matrix {
+        let mut res = Matrix::zero();
+        for i in 0..3 {
+            for j in 0..3 {
+                res.d[i][j] = self.d[j][i].conj();
+            }
+        }
+        res
+    }
+
+    // The largest modulus of an entry.
+    pub fn max_entry(&self) -> f64 {
+        largest(self.d.iter().flatten().map(|z| z.norm()))
+    }
+
+    // Scaled by the largest entry so that the squares don't overflow.
+    pub fn frobenius_norm(&self) -> f64 {
+        let max_entry = self.max_entry();
+        if max_entry == 0.0 || !max_entry.is_finite() {
+            return max_entry;
+        }
+        max_entry * self.d.iter().flatten().map(|z| (z / max_entry).norm_sqr()).sum::<f64>().sqrt()
+    }
+
+    // The matrix divided by roughly its Frobenius norm together with the
+    // divisor, so that eigenvalues can be computed for long words without
+    // overflowing. The divisor is a power of two to keep the division exact.
+    fn normalised(&self) -> (Matrix, f64) {
+        let norm = self.frobenius_norm();
+        if norm == 0.0 || !norm.is_finite() {
+            return (Matrix { d: self.d }, norm);
+        }
+        let divisor = norm.log2().round().exp2();
+        (self.scale(Complex::new(1.0 / divisor, 0.0)), divisor)
+    }
+
+    // The largest singular value, the square root of the largest eigenvalue of
+    // the positive semi-definite matrix A*A.
+    pub fn operator_norm(&self) -> f64 {
+        let (normalised, norm) = self.normalised();
+        let gram = &normalised.conjugate_transpose() * &normalised;
+        norm * largest(gram.eigenvalues().iter().map(|z| z.re)).sqrt()
+    }
+
+    pub fn spectral_radius(&self) -> f64 {
+        let (normalised, norm) = self.normalised();
+        norm * largest(normalised.eigenvalues().iter().map(|z| z.norm()))
+    }
+
+    // The logarithm of the spectral radius, which stays finite even when the
+    // spectral radius itself does not.
+    pub fn log_spectral_radius(&self) -> f64 {
+        let (normalised, norm) = self.normalised();
+        norm.ln() + largest(normalised.eigenvalues().iter().map(|z| z.norm())).ln()
+    }
+
+    pub fn is_scalar(&self, tolerance: f64) -> bool {
+        let mut scalar = Matrix::identity();
+        for i in 0..3 {
+            scalar.d[i][i] = self.d[0][0];
+        }
+        self.approx_eq(&scalar, tolerance)
+    }
+
+    // The difference from the closest scalar matrix, which is the trace
+    // divided by three times the identity.
+    pub fn minus_scalar(&self) -> Matrix {
+        let scalar = self.trace() / 3.0;
+        let mut res = Matrix { d: self.d };
+        for i in 0..3 {
+            res.d[i][i] -= scalar;
+        }
+        res
+    }
+
+    pub fn distance_from_scalar(&self) -> f64 {
+        self.minus_scalar().frobenius_norm()
+    }
+
+    pub fn trace(&self) -> Complex<f64> {
+        (0..3).map(|i| self.d[i][i]).sum()
+    }
+
+    pub fn determinant(&self) -> Complex<f64> {
+        let adjugate = self.adjugate();
+        (0..3).map(|j| self.d[0][j] * adjugate.d[j][0]).sum()
+    }
+
+    // The transpose of the matrix of cofactors. For 3x3 matrices, taking the
+    // remaining rows and columns in cyclic order accounts for the signs.
+    pub fn adjugate(&self) -> Matrix {
+        let mut res = Matrix::zero();
+        for i in 0..3 {
+            for j in 0..3 {
+                let (r1, r2) = ((j + 1) % 3, (j + 2) % 3);
+                let (c1, c2) = ((i + 1) % 3, (i + 2) % 3);
+                res.d[i][j] = self.d[r1][c1] * self.d[r2][c2] - self.d[r1][c2] * self.d[r2][c1];
+            }
+        }
+        res
+    }
+
+    pub fn inverse(&self) -> Option<Matrix> {
+        let determinant = self.determinant();
+        if determinant.norm() == 0.0 {
+            return None;
+        }
+        let mut res = self.adjugate();
+        for row in res.d.iter_mut() {
+            for entry in row.iter_mut() {
+                *entry /= determinant;
+            }
+        }
+        Some(res)
+    }
+
+    // The coefficients of det(λI − A), lowest degree first.
+    pub fn characteristic_polynomial(&self) -> [Complex<f64>; 4] {
+        [-self.determinant(), self.adjugate().trace(), -self.trace(), Complex::new(1.0, 0.0)]
+    }
+
+    // The roots of the characteristic polynomial, found with Cardano's formula
+    // and polished with a Newton step.
+    pub fn eigenvalues(&self) -> [Complex<f64>; 3] {
+        let [c, b, a, _] = self.characteristic_polynomial();
+        // Substituting λ = t − a/3 gives the depressed cubic t³ + pt + r.
+        let p = b - a * a / 3.0;
+        let r = a * a * a * 2.0 / 27.0 - a * b / 3.0 + c;
+        let s = (r * r / 4.0 + p * p * p / 27.0).sqrt();
+        let u3 = if (-r / 2.0 + s).norm() >= (-r / 2.0 - s).norm() { -r / 2.0 + s } else { -r / 2.0 - s };
+        let u = u3.cbrt();
+        let omega = Complex::from_polar(1.0, 2.0 * std::f64::consts::PI / 3.0);
+        let characteristic = |x: Complex<f64>| ((x + a) * x + b) * x + c;
+        let derivative = |x: Complex<f64>| (x * 3.0 + a * 2.0) * x + b;
+        let mut res = [Complex::new(0.0, 0.0); 3];
+        for (k, root) in res.iter_mut().enumerate() {
+            let uk = u * omega.powi(k as i32);
+            let t = if uk.norm() == 0.0 { uk } else { uk - p / (uk * 3.0) };
+            let x = t - a / 3.0;
+            let slope = derivative(x);
+            *root = if slope.norm() > 1e-12 { x - characteristic(x) / slope } else { x };
+        }
+        res
+    }
+
     pub fn flatten(&self) -> [Complex<f64>; 9] {
         let mut res: [Complex<f64>; 9] = [Complex::new(0.0, 0.0); 9];
         for i in 0..3 {
@@ -75,7 +277,14 @@ impl Matrix {
     }
 }
 
+// The largest of some non-negative values, where NaN, the result of an
+// overflow, counts as infinitely large rather than being skipped.
+fn largest(values: impl Iterator<Item = f64>) -> f64 {
+    values.map(|x| if x.is_nan() { f64::INFINITY } else { x }).fold(0.0, f64::max)
+}
+
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod tests {
     use super::*;
     use num::{One, Zero};
@@ -173,5 +382,134 @@ mod tests {
         let actual = &mat1 * &mat2;
         assert_eq!(actual, mat3);
     }
-}
 
+    fn sample_matrix() -> Matrix {
+        let values = [[2.0, 1.0, 0.0], [1.0, 3.0, 1.0], [0.0, 1.0, 4.0]];
+        let mut m = Matrix { d: values.map(|row| row.map(|x| Complex::new(x, 0.0))) };
+        m.d[0][2] = Complex::new(0.0, 1.0);
+        m
+    }
+
+    #[test]
+    fn overflowed_matrices_are_not_equal() {
+        let mut m = Matrix::identity();
+        m.d[0][1] = Complex::new(f64::NAN, 0.0);
+        assert!(!m.approx_eq(&Matrix::identity(), 1e-10));
+        assert!(!m.approx_eq(&m, 1e-10));
+        m.d[0][1] = Complex::new(f64::INFINITY, 0.0);
+        assert!(!m.approx_eq(&m, 1e-10));
+        assert!(!m.is_scalar(1e-10));
+    }
+
+    #[test]
+    fn metrics_vanish_at_identity() {
+        let identity = Matrix::identity();
+        for metric in [Metric::Frobenius, Metric::Operator, Metric::LogMaxEntry, Metric::LogSpectralRadius, Metric::Projective] {
+            assert!(identity.distance(metric).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn metrics_are_computed() {
+        let mut m = Matrix::identity();
+        m.d[0][0] = Complex::new(4.0, 0.0);
+        m.d[1][1] = Complex::new(0.25, 0.0);
+        m.d[0][1] = Complex::new(0.0, 3.0);
+        assert!((m.distance(Metric::Frobenius) - (9.0f64 + 0.5625 + 9.0).sqrt()).abs() < 1e-12);
+        assert!((m.distance(Metric::LogMaxEntry) - 4f64.ln()).abs() < 1e-12);
+        assert!((m.distance(Metric::LogSpectralRadius) - 4f64.ln()).abs() < 1e-12);
+        assert!((m.frobenius_norm() - (16.0f64 + 0.0625 + 9.0 + 1.0).sqrt()).abs() < 1e-12);
+        // The operator norm is bounded by the Frobenius norm and the largest
+        // entry.
+        let operator = m.distance(Metric::Operator);
+        assert!(operator >= 3.0 && operator <= m.distance(Metric::Frobenius));
+
+        // Scaling leaves the projective distance unchanged.
+        let projective = sample_matrix().distance(Metric::Projective);
+        assert!(projective > 0.0);
+        assert!((sample_matrix().scale(Complex::new(1e6, 2e6)).distance(Metric::Projective) - projective).abs() < 1e-12);
+        assert!(Matrix::identity().scale(Complex::new(0.0, 5.0)).distance(Metric::Projective) < 1e-12);
+    }
+
+    #[test]
+    fn operator_norm_is_largest_singular_value() {
+        let mut m = Matrix::zero();
+        m.d[0][1] = Complex::new(0.0, 3.0);
+        m.d[1][0] = Complex::new(2.0, 0.0);
+        m.d[2][2] = Complex::new(-1.0, 0.0);
+        assert!((m.operator_norm() - 3.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn norms_of_huge_matrices_do_not_vanish() {
+        let mut m = Matrix::zero();
+        m.d[0][0] = Complex::new(1e200, 0.0);
+        m.d[1][1] = Complex::new(2e200, 0.0);
+        m.d[2][2] = Complex::new(-3e200, 0.0);
+        m.d[0][1] = Complex::new(0.0, 1e200);
+        assert!((m.frobenius_norm() / 1e200 - 15f64.sqrt()).abs() < 1e-10);
+        assert!(m.operator_norm() >= 3e200);
+        assert!((m.log_spectral_radius() - 3e200f64.ln()).abs() < 1e-8);
+
+        m.d[2][2] = Complex::new(f64::NAN, 0.0);
+        assert_eq!(m.max_entry(), f64::INFINITY);
+        assert_eq!(m.operator_norm(), f64::INFINITY);
+        assert_eq!(m.distance(Metric::LogSpectralRadius), f64::INFINITY);
+    }
+
+    #[test]
+    fn scalar_matrices_are_recognised() {
+        let mut m = Matrix::identity();
+        assert!(m.is_scalar(1e-10));
+        assert_eq!(m.distance_from_scalar(), 0.0);
+        for i in 0..3 {
+            m.d[i][i] = Complex::new(0.0, 2.0);
+        }
+        assert!(m.is_scalar(1e-10));
+        assert!(m.distance_from_scalar() < 1e-15);
+        m.d[1][1] = Complex::new(0.0, 5.0);
+        assert!(!m.is_scalar(1e-10));
+        // The closest scalar matrix is 3i, leaving −i, 2i and −i on the diagonal.
+        assert!((m.distance_from_scalar() - 6f64.sqrt()).abs() < 1e-12);
+        assert!(!sample_matrix().is_scalar(1e-10));
+    }
+
+    #[test]
+    fn determinant_and_trace() {
+        let m = sample_matrix();
+        assert_eq!(m.trace(), Complex::new(9.0, 0.0));
+        // 2(12 − 1) − (4 − 0) + i(1 − 0) = 18 + i
+        assert!((m.determinant() - Complex::new(18.0, 1.0)).norm() < 1e-12);
+        assert_eq!(Matrix::identity().determinant(), Complex::one());
+    }
+
+    #[test]
+    fn inverse_multiplies_to_identity() {
+        let m = sample_matrix();
+        let inverse = m.inverse().unwrap();
+        assert_eq!(&m * &inverse, Matrix::identity());
+        assert_eq!(&inverse * &m, Matrix::identity());
+        assert!(Matrix::zero().inverse().is_none());
+    }
+
+    #[test]
+    fn eigenvalues_are_roots_of_characteristic_polynomial() {
+        let m = sample_matrix();
+        let polynomial = m.characteristic_polynomial();
+        assert_eq!(polynomial[3], Complex::one());
+        assert_eq!(polynomial[2], Complex::new(-9.0, 0.0));
+        for eigenvalue in m.eigenvalues().iter() {
+            let value: Complex<f64> = polynomial.iter().rev().fold(Complex::zero(), |acc, c| acc * eigenvalue + c);
+            assert!(value.norm() < 1e-9);
+        }
+        let product: Complex<f64> = m.eigenvalues().iter().product();
+        assert!((product - m.determinant()).norm() < 1e-9);
+    }
+
+    #[test]
+    fn eigenvalues_of_identity_are_one() {
+        for eigenvalue in Matrix::identity().eigenvalues().iter() {
+            assert!((eigenvalue - Complex::one()).norm() < 1e-12);
+        }
+    }
+}
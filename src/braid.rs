@@ -0,0 +1,333 @@
+use crate::group::Direction;
+use crate::word;
+
+// Braids on four strands are stored as lists of non-zero integers, where i
+// stands for the Artin generator σᵢ and −i for its inverse.
+//
+// Let a = σ₁σ₃⁻¹ and b = σ₂σ₁σ₃⁻¹σ₂⁻¹. These generate a free subgroup of B₄,
+// namely the kernel of the map B₄ → B₃ sending σ₁, σ₃ to σ₁ and σ₂ to σ₂, and
+// the matrices in `Group::new` are the reduced Burau images, with t = 1/q, of
+//
+//     North = ab⁻¹, South = ba⁻¹, East = a, West = a⁻¹.
+pub fn direction_to_braid(direction: &Direction) -> Vec<i32> {
+    match direction {
+        Direction::North => vec![1, -3, 2, 3, -1, -2],
+        Direction::South => vec![2, 1, -3, -2, 3, -1],
+        Direction::East => vec![1, -3],
+        Direction::West => vec![3, -1]
+    }
+}
+
+pub fn word_to_braid(word: &[Direction]) -> Vec<i32> {
+    free_reduce(word.iter().flat_map(direction_to_braid))
+}
+
+// Partial inverse of `word_to_braid`: returns the reduced word whose braid
+// equals the given one, or `None` if the braid does not lie in the subgroup
+// generated by a and b.
+pub fn braid_to_word(braid: &[i32]) -> Option<Vec<Direction>> {
+    // Write the braid as u·s with u a word in a and b, and s a braid in σ₁, σ₂
+    // only, using σ₃ = a⁻¹σ₁ and σ₃⁻¹ = σ₁⁻¹a to move the σ₃'s into u.
+    let mut u: Vec<i32> = vec![];
+    let mut s: Vec<i32> = vec![];
+    for &generator in braid {
+        match generator {
+            3 => {
+                u.extend(conjugate(&s, &[-1]));
+                s.push(1);
+            },
+            -3 => {
+                s.push(-1);
+                u.extend(conjugate(&s, &[1]));
+            },
+            1 | -1 | 2 | -2 => s.push(generator),
+            _ => return None
+        }
+        u = free_reduce(u);
+        s = free_reduce(s);
+    }
+    if !is_trivial_in_b3(&s)? {
+        return None;
+    }
+    let directions: Vec<Direction> = u.iter().flat_map(|letter| match letter {
+        1 => vec![Direction::East],
+        -1 => vec![Direction::West],
+        2 => vec![Direction::South, Direction::East],
+        _ => vec![Direction::West, Direction::North]
+    }).collect();
+    Some(word::free_reduce(&directions))
+}
+
+// Image of a word in a (±1) and b (±2) under conjugation by a braid in σ₁ and
+// σ₂, that is, the word representing s·x·s⁻¹.
+fn conjugate(s: &[i32], x: &[i32]) -> Vec<i32> {
+    let mut res = x.to_vec();
+    for &generator in s.iter().rev() {
+        res = free_reduce(res.iter().flat_map(|&letter| {
+            let image: &[i32] = match (generator, letter.abs()) {
+                (1, 1) | (-1, 1) => &[1],
+                (1, _) => &[2, -1],
+                (-1, _) => &[2, 1],
+                (2, 1) => &[2],
+                (2, _) => &[2, -1, 2],
+                (_, 1) => &[1, -2, 1],
+                (_, _) => &[1]
+            };
+            if letter > 0 {
+                image.to_vec()
+            } else {
+                image.iter().rev().map(|l| -l).collect()
+            }
+        }));
+    }
+    res
+}
+
+// Decides triviality of a braid in σ₁ and σ₂ through the map B₃ → SL(2, ℤ),
+// whose kernel is generated by the fourth power of the half twist, together
+// with the exponent sum. Returns `None` if the matrix entries overflow.
+fn is_trivial_in_b3(s: &[i32]) -> Option<bool> {
+    let mut m: [[i64; 2]; 2] = [[1, 0], [0, 1]];
+    for &generator in s {
+        let g: [[i64; 2]; 2] = match generator {
+            1 => [[1, 1], [0, 1]],
+            -1 => [[1, -1], [0, 1]],
+            2 => [[1, 0], [-1, 1]],
+            _ => [[1, 0], [1, 1]]
+        };
+        let mut product = [[0; 2]; 2];
+        for i in 0..2 {
+            for j in 0..2 {
+                product[i][j] = m[i][0].checked_mul(g[0][j])?
+                    .checked_add(m[i][1].checked_mul(g[1][j])?)?;
+            }
+        }
+        m = product;
+    }
+    let exponent_sum: i32 = s.iter().map(|g| g.signum()).sum();
+    Some(m == [[1, 0], [0, 1]] && exponent_sum == 0)
+}
+
+pub fn free_reduce<I: IntoIterator<Item = i32>>(letters: I) -> Vec<i32> {
+    let mut res: Vec<i32> = vec![];
+    for letter in letters {
+        if res.last() == Some(&-letter) {
+            res.pop();
+        } else {
+            res.push(letter);
+        }
+    }
+    res
+}
+
+// Dehornoy's handle reduction. A σᵢ-handle is a subword σᵢᵉ·w·σᵢ⁻ᵉ in which w
+// contains no σⱼ with j ≤ i; reducing it deletes the outer letters and replaces
+// each σᵢ₊₁ᵈ in w by σᵢ₊₁⁻ᵉσᵢᵈσᵢ₊₁ᵉ. Always reducing the handle that ends first
+// guarantees termination, and the result is empty exactly when the braid is
+// trivial.
+pub fn handle_reduce(braid: &[i32]) -> Vec<i32> {
+    let mut res = braid.to_vec();
+    while let Some((start, end)) = first_handle(&res) {
+        let index = res[start].abs();
+        let e = res[start].signum();
+        let mut reduced = res[..start].to_vec();
+        for &letter in &res[start + 1..end] {
+            if letter.abs() == index + 1 {
+                reduced.extend(&[-e * (index + 1), letter.signum() * index, e * (index + 1)]);
+            } else {
+                reduced.push(letter);
+            }
+        }
+        reduced.extend(&res[end + 1..]);
+        res = reduced;
+    }
+    res
+}
+
+fn first_handle(braid: &[i32]) -> Option<(usize, usize)> {
+    // last[i] is the position of the most recent letter σᵢ^±1.
+    let mut last: Vec<Option<usize>> = vec![];
+    for (end, &letter) in braid.iter().enumerate() {
+        let index = letter.unsigned_abs() as usize;
+        if last.len() <= index {
+            last.resize(index + 1, None);
+        }
+        let blocker = last[..index].iter().flatten().max();
+        if let Some(start) = last[index] {
+            let blocked = blocker.is_some_and(|b| *b > start);
+            if !blocked && braid[start] == -letter {
+                return Some((start, end));
+            }
+        }
+        last[index] = Some(end);
+    }
+    None
+}
+
+pub fn is_trivial(braid: &[i32]) -> bool {
+    handle_reduce(braid).is_empty()
+}
+
+pub fn braid_to_string(braid: &[i32]) -> String {
+    const SUBSCRIPTS: [char; 10] = ['₀', '₁', '₂', '₃', '₄', '₅', '₆', '₇', '₈', '₉'];
+    braid.iter().map(|g| {
+        let index: String = g.abs().to_string().chars()
+            .map(|c| SUBSCRIPTS[c.to_digit(10).unwrap() as usize]).collect();
+        format!("σ{}{}", index, if *g < 0 { "⁻¹" } else { "" })
+    }).collect()
+}
+
+// Parses braids either as lists of integers, such as "1 -3 2", or in the
+// notation of `braid_to_string`, also allowing "s" for "σ", ASCII indices and
+// "^-1" for "⁻¹".
+pub fn parse_braid(s: &str) -> Option<Vec<i32>> {
+    let mut res = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | ',' | '\t' | '\n' => continue,
+            'σ' | 's' => {
+                let index = match chars.next()? {
+                    c @ '1'..='3' => c.to_digit(10)? as i32,
+                    '₁' => 1,
+                    '₂' => 2,
+                    '₃' => 3,
+                    _ => return None
+                };
+                let inverted = match chars.peek() {
+                    Some('⁻') => {
+                        chars.next();
+                        chars.next() == Some('¹')
+                    },
+                    Some('^') => {
+                        chars.next();
+                        chars.next() == Some('-') && chars.next() == Some('1')
+                    },
+                    _ => false
+                };
+                res.push(if inverted { -index } else { index });
+            },
+            '-' | '1'..='9' => {
+                let mut token = c.to_string();
+                while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    token.push(*d);
+                    chars.next();
+                }
+                let generator: i32 = token.parse().ok()?;
+                if generator == 0 || generator.abs() > 3 {
+                    return None;
+                }
+                res.push(generator);
+            },
+            _ => return None
+        }
+    }
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algebra::Matrix;
+    use crate::group::Group;
+    use num::Complex;
+
+    fn burau(braid: &[i32], q: &Complex<f64>) -> Matrix {
+        let t = q.inv();
+        let one = Complex::new(1.0, 0.0);
+        let mut res = Matrix::identity();
+        for &g in braid {
+            let mut m = Matrix::identity();
+            let i = (g.abs() - 1) as usize;
+            match (g.abs(), g > 0) {
+                (1, true) => { m.d[0][0] = -t; m.d[0][1] = one; },
+                (1, false) => { m.d[0][0] = -q; m.d[0][1] = *q; },
+                (3, true) => { m.d[2][1] = t; m.d[2][2] = -t; },
+                (3, false) => { m.d[2][1] = one; m.d[2][2] = -q; },
+                (_, true) => { m.d[i][0] = t; m.d[i][1] = -t; m.d[i][2] = one; },
+                (_, false) => { m.d[i][0] = one; m.d[i][1] = -q; m.d[i][2] = *q; }
+            }
+            res = &res * &m;
+        }
+        res
+    }
+
+    #[test]
+    fn directions_are_burau_images_of_their_braids() {
+        let q = Complex::new(0.7, 0.4);
+        for direction in &[Direction::North, Direction::South, Direction::East, Direction::West] {
+            let mut group = Group::new(&q);
+            group.push(direction);
+            let expected = burau(&direction_to_braid(direction), &q);
+            for (x, y) in group.flatten().iter().zip(expected.flatten().iter()) {
+                assert!((x - y).norm() < 1e-10);
+            }
+        }
+    }
+
+    #[test]
+    fn braid_to_word_inverts_word_to_braid() {
+        use Direction::*;
+        let words = vec![
+            vec![],
+            vec![North],
+            vec![South, East],
+            vec![West, North, North, East, South, West, West],
+            vec![East, East, North, West, South, South, East, North]
+        ];
+        for word in words {
+            assert_eq!(braid_to_word(&word_to_braid(&word)), Some(word));
+        }
+    }
+
+    #[test]
+    fn braid_to_word_handles_braid_relations() {
+        // a·b = σ₁σ₃⁻¹σ₂σ₁σ₃⁻¹σ₂⁻¹, rewritten with σ₁σ₂σ₁ = σ₂σ₁σ₂.
+        let braid = vec![-3, 2, 1, 2, -3, -2];
+        assert_eq!(braid_to_word(&braid), Some(vec![Direction::East, Direction::South, Direction::East]));
+    }
+
+    #[test]
+    fn braid_to_word_rejects_braids_outside_subgroup() {
+        assert_eq!(braid_to_word(&[1]), None);
+        assert_eq!(braid_to_word(&[3, 2]), None);
+        assert_eq!(braid_to_word(&[4]), None);
+    }
+
+    #[test]
+    fn handle_reduction_detects_trivial_braids() {
+        assert!(is_trivial(&[]));
+        assert!(is_trivial(&[1, -1]));
+        assert!(is_trivial(&[1, 2, 1, -2, -1, -2]));
+        assert!(is_trivial(&[1, 3, -1, -3]));
+        assert!(is_trivial(&[2, 3, 2, -3, -2, -3, 1, -1]));
+        assert!(!is_trivial(&[1]));
+        assert!(!is_trivial(&[1, 2, -1, -2]));
+        assert!(!is_trivial(&[1, 2, 1, -2, -1, 2]));
+    }
+
+    #[test]
+    fn handle_reduction_of_reduced_words_is_non_trivial() {
+        use Direction::*;
+        assert!(!is_trivial(&word_to_braid(&[North, East, South, West])));
+        assert!(!is_trivial(&word_to_braid(&[North, North])));
+        // Reduction leaves non-empty braids without handles.
+        let reduced = handle_reduce(&word_to_braid(&[North, North]));
+        assert_eq!(reduced, vec![-3, -2, 1, -3, 1, -3, 2, 3]);
+        assert_eq!(first_handle(&reduced), None);
+        let reduced = handle_reduce(&word_to_braid(&[North, East, South, West]));
+        assert_eq!(reduced, vec![-3, -2, -3, -3, -2, -1, 2, 3, 2, 3, 2, 3, 3, -1]);
+        assert_eq!(first_handle(&reduced), None);
+    }
+
+    #[test]
+    fn braid_strings_round_trip() {
+        let braid = vec![1, -3, 2, 3, -1, -2];
+        assert_eq!(braid_to_string(&braid), "σ₁σ₃⁻¹σ₂σ₃σ₁⁻¹σ₂⁻¹");
+        assert_eq!(parse_braid(&braid_to_string(&braid)), Some(braid.clone()));
+        assert_eq!(parse_braid("1 -3 2 3 -1 -2"), Some(braid.clone()));
+        assert_eq!(parse_braid("s1 s3^-1 s2 s3 s1^-1 s2^-1"), Some(braid));
+        assert_eq!(parse_braid("σ₄"), None);
+        assert_eq!(parse_braid("x"), None);
+    }
+}
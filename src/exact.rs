@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::fmt;
+use std::ops::{Add, Mul};
+
+use num::{BigInt, BigRational, Complex, One, Zero};
+
+use crate::group::{generator_polynomials, Direction};
+use crate::render::{self, Notation, Symbol};
+
+// Values of q for which matrix entries can be computed exactly.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExactValue {
+    Integer(i64),
+    RootOfUnity { order: u32, power: u32 }
+}
+
+pub fn recognise(q: &Complex<f64>) -> Option<ExactValue> {
+    const TOLERANCE: f64 = 1e-10;
+    if q.im.abs() < TOLERANCE && (q.re - q.re.round()).abs() < TOLERANCE && q.re.round() != 0.0 {
+        return Some(ExactValue::Integer(q.re.round() as i64));
+    }
+    if (q.norm() - 1.0).abs() > TOLERANCE {
+        return None;
+    }
+    let turns = q.arg().rem_euclid(2.0 * PI) / (2.0 * PI);
+    (1..=60).find_map(|order| {
+        let power = (turns * order as f64).round();
+        if (turns * order as f64 - power).abs() < TOLERANCE {
+            Some(ExactValue::RootOfUnity { order, power: power as u32 % order })
+        } else {
+            None
+        }
+    })
+}
+
+// A Laurent polynomial in q with integer coefficients, given by its non-zero
+// coefficients indexed by exponent.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Laurent {
+    pub terms: BTreeMap<i32, BigInt>
+}
+
+impl Laurent {
+    pub fn from_summands(summands: &[(i32, i32)]) -> Laurent {
+        let mut res = Laurent::default();
+        for (exp, coef) in summands {
+            res.add_term(*exp, BigInt::from(*coef));
+        }
+        res
+    }
+
+    // The polynomial with the given coefficients, lowest degree first.
+    pub fn from_coefficients(coefficients: &[BigInt]) -> Laurent {
+        let mut res = Laurent::default();
+        for (exp, coef) in coefficients.iter().enumerate() {
+            res.add_term(exp as i32, coef.clone());
+        }
+        res
+    }
+
+    fn add_term(&mut self, exp: i32, coef: BigInt) {
+        let entry = self.terms.entry(exp).or_insert_with(BigInt::zero);
+        *entry += coef;
+        if entry.is_zero() {
+            self.terms.remove(&exp);
+        }
+    }
+
+    pub fn write(&self, notation: Notation) -> String {
+        let terms: Vec<(BigInt, Option<(Symbol, i64)>)> = self.terms.iter()
+            .map(|(exp, coef)| (coef.clone(), Some((Symbol::Q, *exp as i64))))
+            .collect();
+        render::sum(&terms, notation)
+    }
+
+    // The value of the polynomial at q, written exactly.
+    pub fn evaluate(&self, q: ExactValue, notation: Notation) -> String {
+        match q {
+            ExactValue::Integer(n) => {
+                // By Horner's rule over the integers, clearing negative powers
+                // of n into the denominator, so that only one fraction needs
+                // to be reduced.
+                let n = BigInt::from(n);
+                let lowest = self.terms.keys().next().map_or(0, |exp| (*exp).min(0));
+                let highest = self.terms.keys().next_back().map_or(0, |exp| *exp);
+                let mut numerator = BigInt::zero();
+                for exp in (lowest..=highest).rev() {
+                    numerator = numerator * &n + self.terms.get(&exp).cloned().unwrap_or_default();
+                }
+                let value = BigRational::new(numerator, n.pow((-lowest) as u32));
+                render::fraction(value.numer(), value.denom(), notation)
+            }
+            ExactValue::RootOfUnity { order, power } => {
+                let symbol = if order == 4 { Symbol::I } else { Symbol::Zeta(order) };
+                let terms: Vec<(BigInt, Option<(Symbol, i64)>)> = self.evaluate_at_root_of_unity(order, power)
+                    .into_iter().enumerate()
+                    .map(|(i, coef)| (coef, Some((symbol, i as i64))))
+                    .collect();
+                render::sum(&terms, notation)
+            }
+        }
+    }
+
+    // The coordinates of the value at q = ζ^power in the basis 1, ζ, ζ², … of
+    // the cyclotomic integers, where ζ = exp(2πi/order).
+    fn evaluate_at_root_of_unity(&self, order: u32, power: u32) -> Vec<BigInt> {
+        let n = order as i64;
+        let mut res = vec![BigInt::zero(); order as usize];
+        for (exp, coef) in &self.terms {
+            res[(*exp as i64 * power as i64).rem_euclid(n) as usize] += coef;
+        }
+        let modulus = cyclotomic(order);
+        let degree = modulus.len() - 1;
+        for i in (degree..res.len()).rev() {
+            let coef = std::mem::take(&mut res[i]);
+            for (j, m) in modulus.iter().enumerate().take(degree) {
+                res[i - degree + j] -= &coef * m;
+            }
+        }
+        res.truncate(degree);
+        res
+    }
+}
+
+impl Add for &Laurent {
+    type Output = Laurent;
+
+    fn add(self, rhs: &Laurent) -> Laurent {
+        let mut res = self.clone();
+        for (exp, coef) in &rhs.terms {
+            res.add_term(*exp, coef.clone());
+        }
+        res
+    }
+}
+
+impl Mul for &Laurent {
+    type Output = Laurent;
+
+    fn mul(self, rhs: &Laurent) -> Laurent {
+        let mut res = Laurent::default();
+        for (exp1, coef1) in &self.terms {
+            for (exp2, coef2) in &rhs.terms {
+                res.add_term(exp1 + exp2, coef1 * coef2);
+            }
+        }
+        res
+    }
+}
+
+impl fmt::Display for Laurent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.write(Notation::Unicode))
+    }
+}
+
+// Matrices with Laurent polynomial entries, tracking the product of a word
+// for symbolic q.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LaurentMatrix {
+    pub d: [[Laurent; 3]; 3]
+}
+
+impl LaurentMatrix {
+    pub fn write(&self, notation: Notation) -> Vec<String> {
+        self.d.iter().flatten().map(|p| p.write(notation)).collect()
+    }
+
+    pub fn trace(&self) -> Laurent {
+        (0..3).fold(Laurent::default(), |acc, i| &acc + &self.d[i][i])
+    }
+
+    pub fn identity() -> LaurentMatrix {
+        let mut d: [[Laurent; 3]; 3] = Default::default();
+        for (i, row) in d.iter_mut().enumerate() {
+            row[i] = Laurent::from_summands(&[(0, 1)]);
+        }
+        LaurentMatrix { d }
+    }
+
+    pub fn generator(direction: &Direction) -> LaurentMatrix {
+        let polynomials = generator_polynomials(direction);
+        LaurentMatrix { d: polynomials.map(|row| row.map(Laurent::from_summands)) }
+    }
+}
+
+impl Mul for &LaurentMatrix {
+    type Output = LaurentMatrix;
+
+    fn mul(self, rhs: &LaurentMatrix) -> LaurentMatrix {
+        let mut d: [[Laurent; 3]; 3] = Default::default();
+        for (i, row) in d.iter_mut().enumerate() {
+            for (j, entry) in row.iter_mut().enumerate() {
+                for k in 0..3 {
+                    *entry = &*entry + &(&self.d[i][k] * &rhs.d[k][j]);
+                }
+            }
+        }
+        LaurentMatrix { d }
+    }
+}
+
+// The coefficients of the cyclotomic polynomial Φₙ, lowest degree first.
+pub fn cyclotomic(n: u32) -> Vec<BigInt> {
+    // xⁿ − 1 is the product of Φ_d for the divisors d of n.
+    let mut res = vec![BigInt::zero(); n as usize + 1];
+    res[0] = -BigInt::one();
+    res[n as usize] = BigInt::one();
+    for d in (1..n).filter(|d| n.is_multiple_of(*d)) {
+        res = divide(&res, &cyclotomic(d));
+    }
+    res
+}
+
+// Exact division by a monic polynomial.
+fn divide(dividend: &[BigInt], divisor: &[BigInt]) -> Vec<BigInt> {
+    let mut remainder = dividend.to_vec();
+    let degree = divisor.len() - 1;
+    let mut quotient = vec![BigInt::zero(); dividend.len() - degree];
+    for i in (0..quotient.len()).rev() {
+        let coef = remainder[i + degree].clone();
+        for (j, d) in divisor.iter().enumerate() {
+            remainder[i + j] -= &coef * d;
+        }
+        quotient[i] = coef;
+    }
+    quotient
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::ToPrimitive;
+
+    #[test]
+    fn recognise_finds_integers_and_roots_of_unity() {
+        assert_eq!(recognise(&Complex::new(-1.0, 0.0)), Some(ExactValue::Integer(-1)));
+        assert_eq!(recognise(&Complex::new(3.0, 0.0)), Some(ExactValue::Integer(3)));
+        assert_eq!(recognise(&Complex::new(0.0, 1.0)), Some(ExactValue::RootOfUnity { order: 4, power: 1 }));
+        assert_eq!(recognise(&Complex::from_polar(1.0, 2.0 * PI / 5.0)), Some(ExactValue::RootOfUnity { order: 5, power: 1 }));
+        assert_eq!(recognise(&Complex::from_polar(1.0, -2.0 * PI / 3.0)), Some(ExactValue::RootOfUnity { order: 3, power: 2 }));
+        assert_eq!(recognise(&Complex::new(2.9, 0.1)), None);
+    }
+
+    #[test]
+    fn cyclotomic_polynomials_are_computed() {
+        let to_ints = |p: Vec<BigInt>| p.iter().map(|c| c.to_i32().unwrap()).collect::<Vec<_>>();
+        assert_eq!(to_ints(cyclotomic(1)), vec![-1, 1]);
+        assert_eq!(to_ints(cyclotomic(4)), vec![1, 0, 1]);
+        assert_eq!(to_ints(cyclotomic(5)), vec![1, 1, 1, 1, 1]);
+        assert_eq!(to_ints(cyclotomic(6)), vec![1, -1, 1]);
+    }
+
+    #[test]
+    fn laurent_polynomials_are_displayed() {
+        assert_eq!(Laurent::default().to_string(), "0");
+        assert_eq!(Laurent::from_summands(&[(-1, -1), (1, 1)]).to_string(), "−𝑞⁻¹ + 𝑞");
+        assert_eq!(Laurent::from_summands(&[(0, 1), (2, -3)]).to_string(), "1 − 3𝑞²");
+        let p = Laurent::from_summands(&[(0, 1), (1, -1)]);
+        assert_eq!((&p * &p).to_string(), "1 − 2𝑞 + 𝑞²");
+        assert_eq!((&p + &Laurent::from_summands(&[(1, 1)])).to_string(), "1");
+    }
+
+    #[test]
+    fn laurent_polynomials_are_evaluated_exactly() {
+        let p = Laurent::from_summands(&[(-1, -1), (1, 1)]);
+        assert_eq!(p.evaluate(ExactValue::Integer(1), Notation::Unicode), "0");
+        assert_eq!(p.evaluate(ExactValue::Integer(2), Notation::Unicode), "3/2");
+        assert_eq!(Laurent::from_summands(&[(-2, -5)]).evaluate(ExactValue::Integer(-2), Notation::Unicode), "−5/4");
+        assert_eq!(p.evaluate(ExactValue::RootOfUnity { order: 4, power: 1 }, Notation::Unicode), "2𝑖");
+        assert_eq!(Laurent::from_summands(&[(-1, -1)]).evaluate(ExactValue::RootOfUnity { order: 4, power: 1 }, Notation::Unicode), "𝑖");
+        // ζ₃⁻¹ = ζ₃² = −1 − ζ₃.
+        assert_eq!(p.evaluate(ExactValue::RootOfUnity { order: 3, power: 1 }, Notation::Unicode), "1 + 2ζ₃");
+        assert_eq!(p.evaluate(ExactValue::RootOfUnity { order: 5, power: 1 }, Notation::Unicode), "1 + 2ζ₅ + ζ₅² + ζ₅³");
+        assert_eq!(p.evaluate(ExactValue::RootOfUnity { order: 3, power: 2 }, Notation::Unicode), "−1 − 2ζ₃");
+    }
+
+    #[test]
+    fn products_of_generators_are_exact() {
+        let north = LaurentMatrix::generator(&Direction::North);
+        let south = LaurentMatrix::generator(&Direction::South);
+        assert_eq!(&north * &south, LaurentMatrix::identity());
+        assert_ne!(&north * &north, LaurentMatrix::identity());
+    }
+
+    #[test]
+    fn traces_are_computed() {
+        let east = LaurentMatrix::generator(&Direction::East);
+        assert_eq!(LaurentMatrix::identity().trace().to_string(), "3");
+        assert_eq!(east.trace().to_string(), "−𝑞⁻¹ + 1 − 𝑞");
+    }
+}
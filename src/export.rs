@@ -0,0 +1,187 @@
+use num::Complex;
+use wasm_bindgen::prelude::*;
+
+use crate::exact::{recognise, ExactValue};
+use crate::group::{generator_polynomials, Direction};
+use crate::word;
+
+#[repr(u8)]
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    Gap,
+    Magma,
+    Sage
+}
+
+// Renders a Laurent polynomial in q in a syntax understood by GAP, Magma and
+// Sage alike.
+fn polynomial_to_string(summands: &[(i32, i32)]) -> String {
+    if summands.is_empty() {
+        return "0".to_owned();
+    }
+    let mut res = String::new();
+    for (i, (exp, coef)) in summands.iter().enumerate() {
+        let sign = if *coef < 0 { "-" } else if i > 0 { "+" } else { "" };
+        if i > 0 {
+            res.push(' ');
+        }
+        res.push_str(sign);
+        if i > 0 {
+            res.push(' ');
+        }
+        let coef = coef.abs();
+        let power = match exp {
+            0 => "".to_owned(),
+            1 => "q".to_owned(),
+            _ => format!("q^{}", exp)
+        };
+        res.push_str(&match (coef, power.is_empty()) {
+            (_, true) => coef.to_string(),
+            (1, false) => power,
+            (_, false) => format!("{}*{}", coef, power)
+        });
+    }
+    res
+}
+
+fn matrix_rows(direction: &Direction) -> String {
+    generator_polynomials(direction).iter().map(|row| {
+        let entries: Vec<String> = row.iter().map(|p| polynomial_to_string(p)).collect();
+        format!("[{}]", entries.join(", "))
+    }).collect::<Vec<String>>().join(", ")
+}
+
+fn root_of_unity_power(power: u32) -> String {
+    if power == 1 { "z".to_owned() } else { format!("z^{}", power) }
+}
+
+const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
+
+fn product(word: &[Direction], separator: &str, name: impl Fn(&Direction) -> String) -> String {
+    let factors: Vec<String> = word.iter().map(name).collect();
+    factors.join(separator)
+}
+
+// Builds a script which defines the four generator matrices and asserts that
+// the product along the word is the identity, either over the Laurent
+// polynomials in q, or for each of the given values of q.
+pub fn export(format: ExportFormat, word: &[Direction], qs: Option<&[Complex<f64>]>) -> Result<String, String> {
+    if word.is_empty() {
+        return Err("The word is empty".to_owned());
+    }
+    let values = match qs {
+        None => None,
+        Some(qs) => Some(qs.iter().map(|q| recognise(q).ok_or(
+            format!("q = {} + {}i has no exact representation", q.re, q.im))).collect::<Result<Vec<_>, _>>()?)
+    };
+    let word_string = word::word_to_string(word);
+    Ok(match format {
+        ExportFormat::Gap => gap(word, &word_string, values),
+        ExportFormat::Magma => magma(word, &word_string, values),
+        ExportFormat::Sage => sage(word, &word_string, values)
+    })
+}
+
+fn gap(word: &[Direction], word_string: &str, values: Option<Vec<ExactValue>>) -> String {
+    let mut res = format!("# Checks that {} is a relation.\n", word_string);
+    res.push_str("BurauGenerators := function(q)\n    return rec(\n");
+    let definitions: Vec<String> = DIRECTIONS.iter()
+        .map(|d| format!("        {} := [{}]", d.letter(), matrix_rows(d))).collect();
+    res.push_str(&definitions.join(",\n"));
+    res.push_str(");\nend;\n\n");
+    res.push_str("CheckRelation := function(q)\n    local g;\n    g := BurauGenerators(q);\n");
+    res.push_str(&format!("    return IsOne({});\nend;\n\n", product(word, "*", |d| format!("g.{}", d.letter()))));
+    match values {
+        None => res.push_str("q := Indeterminate(Rationals, \"q\");\nAssert(0, CheckRelation(q));\n"),
+        Some(values) => for value in values {
+            let q = match value {
+                ExactValue::Integer(n) => n.to_string(),
+                ExactValue::RootOfUnity { order, power: 1 } => format!("E({})", order),
+                ExactValue::RootOfUnity { order, power } => format!("E({})^{}", order, power)
+            };
+            res.push_str(&format!("Assert(0, CheckRelation({}));\n", q));
+        }
+    }
+    res
+}
+
+fn magma(word: &[Direction], word_string: &str, values: Option<Vec<ExactValue>>) -> String {
+    let mut res = format!("// Checks that {} is a relation.\n", word_string);
+    res.push_str("function BurauGenerators(q)\n    K := Parent(q);\n");
+    for d in DIRECTIONS.iter() {
+        let rows = matrix_rows(d).replace(['[', ']'], "");
+        res.push_str(&format!("    {} := Matrix(K, 3, 3, [{}]);\n", d.letter(), rows));
+    }
+    res.push_str("    return N, S, E, W;\nend function;\n\n");
+    let check = format!("N, S, E, W := BurauGenerators(q);\nassert {} eq IdentityMatrix(Parent(q), 3);\n",
+        product(word, "*", |d| d.letter().to_string()));
+    match values {
+        None => {
+            res.push_str("F<q> := FunctionField(Rationals());\n");
+            res.push_str(&check);
+        },
+        Some(values) => for value in values {
+            match value {
+                ExactValue::Integer(n) => res.push_str(&format!("q := Rationals()!{};\n", n)),
+                ExactValue::RootOfUnity { order, power } => res.push_str(
+                    &format!("K<z> := CyclotomicField({});\nq := {};\n", order, root_of_unity_power(power)))
+            }
+            res.push_str(&check);
+        }
+    }
+    res
+}
+
+fn sage(word: &[Direction], word_string: &str, values: Option<Vec<ExactValue>>) -> String {
+    let mut res = format!("# Checks that {} is a relation.\n", word_string);
+    res.push_str("def burau_generators(q):\n    return {\n");
+    let definitions: Vec<String> = DIRECTIONS.iter()
+        .map(|d| format!("        '{}': matrix([{}])", d.letter(), matrix_rows(d))).collect();
+    res.push_str(&definitions.join(",\n"));
+    res.push_str("\n    }\n\n");
+    res.push_str(&format!("def check(q):\n    g = burau_generators(q)\n    return ({}).is_one()\n\n",
+        product(word, " * ", |d| format!("g['{}']", d.letter()))));
+    match values {
+        None => res.push_str("R.<q> = LaurentPolynomialRing(ZZ)\nassert check(q)\n"),
+        Some(values) => for value in values {
+            match value {
+                ExactValue::Integer(n) => res.push_str(&format!("assert check(QQ({}))\n", n)),
+                ExactValue::RootOfUnity { order, power } => res.push_str(
+                    &format!("K.<z> = CyclotomicField({})\nassert check({})\n", order, root_of_unity_power(power)))
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polynomials_are_rendered() {
+        assert_eq!(polynomial_to_string(&[]), "0");
+        assert_eq!(polynomial_to_string(&[(0, 1)]), "1");
+        assert_eq!(polynomial_to_string(&[(-1, -1), (1, 1)]), "-q^-1 + q");
+        assert_eq!(polynomial_to_string(&[(0, 1), (1, -1)]), "1 - q");
+        assert_eq!(polynomial_to_string(&[(2, 3)]), "3*q^2");
+    }
+
+    #[test]
+    fn gap_export_checks_each_value() {
+        let qs = [Complex::new(1.0, 0.0), Complex::new(0.0, 1.0)];
+        let script = export(ExportFormat::Gap, &[Direction::North, Direction::East], Some(&qs)).unwrap();
+        assert!(script.contains("N := [[0, 0, -q^-1], [0, -q, -q^-1 + q], [-1, 0, -q^-1 + 1]]"));
+        assert!(script.contains("return IsOne(g.N*g.E);"));
+        assert!(script.contains("Assert(0, CheckRelation(1));\nAssert(0, CheckRelation(E(4)));"));
+    }
+
+    #[test]
+    fn export_rejects_inexact_values() {
+        let qs = [Complex::new(2.9, 0.0)];
+        assert!(export(ExportFormat::Sage, &[Direction::North], Some(&qs)).is_err());
+        assert!(export(ExportFormat::Sage, &[Direction::North], None).is_ok());
+        assert!(export(ExportFormat::Magma, &[], None).is_err());
+    }
+}
@@ -0,0 +1,476 @@
+use std::f64::consts::{E, PI};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use num::Complex;
+
+// Small arithmetic expressions describing values of q, such as "−1",
+// "exp(2πi/5)", "3 + 0.1i", "sqrt(2)" or "ζ(7)^2". Both the numeric value and
+// the display form shown to players are derived from the parsed expression.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Expression {
+    Number(f64),
+    Constant(Constant),
+    Neg(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Sub(Box<Expression>, Box<Expression>),
+    Mul(Box<Expression>, Box<Expression>),
+    Div(Box<Expression>, Box<Expression>),
+    Pow(Box<Expression>, Box<Expression>),
+    Function(Function, Box<Expression>)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Constant {
+    I,
+    Pi,
+    E
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Function {
+    Exp,
+    Sqrt,
+    // The root of unity ζ(n) = exp(2πi/n).
+    RootOfUnity
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    LeftParen,
+    RightParen
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c.is_ascii_digit() || c == '.' {
+            tokens.push(Token::Number(read_number(&mut chars)?));
+            continue;
+        }
+        if c.is_ascii_alphabetic() {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                name.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Identifier(name));
+            continue;
+        }
+        chars.next();
+        tokens.push(match c {
+            '+' => Token::Plus,
+            '-' | '−' => Token::Minus,
+            '*' | '·' | '×' => Token::Star,
+            '/' => Token::Slash,
+            '^' => Token::Caret,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '𝑖' => Token::Identifier("i".to_owned()),
+            'π' => Token::Identifier("pi".to_owned()),
+            '𝑒' => Token::Identifier("e".to_owned()),
+            '√' => Token::Identifier("sqrt".to_owned()),
+            'ζ' => Token::Identifier("zeta".to_owned()),
+            _ => return Err(format!("Unexpected character '{}'", c))
+        });
+    }
+    Ok(tokens)
+}
+
+fn read_number(chars: &mut Peekable<Chars>) -> Result<f64, String> {
+    let mut literal = String::new();
+    while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+        literal.push(c);
+        chars.next();
+    }
+    // An exponent as in 1e-3, which is only taken as such if digits follow so
+    // that 2e still means twice Euler's number.
+    if let Some(&e) = chars.peek().filter(|c| **c == 'e' || **c == 'E') {
+        let mut lookahead = chars.clone();
+        lookahead.next();
+        let mut exponent = e.to_string();
+        if let Some(&sign) = lookahead.peek().filter(|c| **c == '+' || **c == '-' || **c == '−') {
+            exponent.push(if sign == '+' { '+' } else { '-' });
+            lookahead.next();
+        }
+        if lookahead.peek().is_some_and(|c| c.is_ascii_digit()) {
+            while let Some(&c) = lookahead.peek().filter(|c| c.is_ascii_digit()) {
+                exponent.push(c);
+                lookahead.next();
+            }
+            literal.push_str(&exponent);
+            *chars = lookahead;
+        }
+    }
+    literal.parse().map_err(|_| format!("Invalid number '{}'", literal))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.next() {
+            Some(t) if t == token => Ok(()),
+            _ => Err(format!("Expected {:?}", token))
+        }
+    }
+
+    // expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<Expression, String> {
+        let mut res = self.term()?;
+        loop {
+            match self.peek() {
+                Some(Token::Plus) => {
+                    self.next();
+                    res = Expression::Add(Box::new(res), Box::new(self.term()?));
+                },
+                Some(Token::Minus) => {
+                    self.next();
+                    res = Expression::Sub(Box::new(res), Box::new(self.term()?));
+                },
+                _ => return Ok(res)
+            }
+        }
+    }
+
+    // term := unary (('*' | '/') unary | power)*, where juxtaposition is
+    // multiplication.
+    fn term(&mut self) -> Result<Expression, String> {
+        let mut res = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::Star) => {
+                    self.next();
+                    res = Expression::Mul(Box::new(res), Box::new(self.unary()?));
+                },
+                Some(Token::Slash) => {
+                    self.next();
+                    res = Expression::Div(Box::new(res), Box::new(self.unary()?));
+                },
+                Some(Token::Number(_)) | Some(Token::Identifier(_)) | Some(Token::LeftParen) => {
+                    res = Expression::Mul(Box::new(res), Box::new(self.power()?));
+                },
+                _ => return Ok(res)
+            }
+        }
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<Expression, String> {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.next();
+                Ok(Expression::Neg(Box::new(self.unary()?)))
+            },
+            Some(Token::Plus) => {
+                self.next();
+                self.unary()
+            },
+            _ => self.power()
+        }
+    }
+
+    // power := primary ('^' unary)?
+    fn power(&mut self) -> Result<Expression, String> {
+        let base = self.primary()?;
+        if self.peek() == Some(&Token::Caret) {
+            self.next();
+            return Ok(Expression::Pow(Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some(Token::Number(x)) => Ok(Expression::Number(x)),
+            Some(Token::LeftParen) => {
+                let res = self.expression()?;
+                self.expect(Token::RightParen)?;
+                Ok(res)
+            },
+            Some(Token::Identifier(name)) => {
+                let function = match name.as_str() {
+                    "i" => return Ok(Expression::Constant(Constant::I)),
+                    "pi" => return Ok(Expression::Constant(Constant::Pi)),
+                    "e" => return Ok(Expression::Constant(Constant::E)),
+                    "exp" => Function::Exp,
+                    "sqrt" => Function::Sqrt,
+                    "zeta" => Function::RootOfUnity,
+                    _ => return Err(format!("Unknown name '{}'", name))
+                };
+                // Functions may be applied without parentheses, as in "√2".
+                let argument = if self.peek() == Some(&Token::LeftParen) {
+                    self.primary()?
+                } else {
+                    self.power()?
+                };
+                Ok(Expression::Function(function, Box::new(argument)))
+            },
+            Some(token) => Err(format!("Unexpected {:?}", token)),
+            None => Err("Unexpected end of expression".to_owned())
+        }
+    }
+}
+
+pub fn parse(s: &str) -> Result<Expression, String> {
+    let mut parser = Parser { tokens: tokenize(s)?, position: 0 };
+    let res = parser.expression()?;
+    if parser.position < parser.tokens.len() {
+        return Err(format!("Unexpected {:?}", parser.tokens[parser.position]));
+    }
+    Ok(res)
+}
+
+impl Expression {
+    pub fn evaluate(&self) -> Complex<f64> {
+        match self {
+            Expression::Number(x) => Complex::new(*x, 0.0),
+            Expression::Constant(Constant::I) => Complex::i(),
+            Expression::Constant(Constant::Pi) => Complex::new(PI, 0.0),
+            Expression::Constant(Constant::E) => Complex::new(E, 0.0),
+            // Subtracting from zero avoids negative zeros, which would put
+            // √(−4) on the wrong branch.
+            Expression::Neg(a) => Complex::new(0.0, 0.0) - a.evaluate(),
+            Expression::Add(a, b) => a.evaluate() + b.evaluate(),
+            Expression::Sub(a, b) => a.evaluate() - b.evaluate(),
+            Expression::Mul(a, b) => a.evaluate() * b.evaluate(),
+            Expression::Div(a, b) => a.evaluate() / b.evaluate(),
+            Expression::Pow(a, b) => {
+                let base = a.evaluate();
+                let exponent = b.evaluate();
+                if exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() < 1e9 {
+                    base.powi(exponent.re as i32)
+                } else {
+                    base.powc(exponent)
+                }
+            },
+            Expression::Function(Function::Exp, a) => a.evaluate().exp(),
+            Expression::Function(Function::Sqrt, a) => a.evaluate().sqrt(),
+            Expression::Function(Function::RootOfUnity, a) =>
+                (Complex::new(0.0, 2.0 * PI) / a.evaluate()).exp()
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expression::Add(_, _) | Expression::Sub(_, _) => 1,
+            Expression::Mul(_, _) | Expression::Div(_, _) => 2,
+            Expression::Neg(_) => 3,
+            Expression::Pow(_, _) => 4,
+            _ => 5
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, min_precedence: u8) -> fmt::Result {
+        if self.precedence() < min_precedence {
+            write!(f, "(")?;
+            self.write(f, 0)?;
+            return write!(f, ")");
+        }
+        match self {
+            Expression::Number(x) => write!(f, "{}", x),
+            Expression::Constant(Constant::I) => write!(f, "𝑖"),
+            Expression::Constant(Constant::Pi) => write!(f, "π"),
+            Expression::Constant(Constant::E) => write!(f, "𝑒"),
+            Expression::Neg(a) => {
+                write!(f, "−")?;
+                a.write(f, 2)
+            },
+            Expression::Add(a, b) => {
+                a.write(f, 1)?;
+                write!(f, " + ")?;
+                b.write(f, 2)
+            },
+            Expression::Sub(a, b) => {
+                a.write(f, 1)?;
+                write!(f, " − ")?;
+                b.write(f, 2)
+            },
+            Expression::Mul(a, b) => {
+                a.write(f, 2)?;
+                // Juxtapose unless that would glue two numbers together.
+                let right = format!("{}", Wrapper(b, 4));
+                if !right.starts_with(|c: char| c.is_ascii_digit()) {
+                    write!(f, "{}", right)
+                } else {
+                    write!(f, "·{}", right)
+                }
+            },
+            Expression::Div(a, b) => {
+                a.write(f, 2)?;
+                write!(f, "/")?;
+                b.write(f, 4)
+            },
+            Expression::Pow(a, b) => {
+                a.write(f, 5)?;
+                match superscript(b) {
+                    Some(s) => write!(f, "{}", s),
+                    None => {
+                        write!(f, "^")?;
+                        b.write(f, 5)
+                    }
+                }
+            },
+            Expression::Function(Function::RootOfUnity, a) => match subscript(a) {
+                Some(s) => write!(f, "ζ{}", s),
+                None => {
+                    write!(f, "ζ(")?;
+                    a.write(f, 0)?;
+                    write!(f, ")")
+                }
+            },
+            Expression::Function(Function::Sqrt, a) if a.precedence() == 5 => {
+                write!(f, "√")?;
+                a.write(f, 5)
+            },
+            Expression::Function(function, a) => {
+                let name = match function {
+                    Function::Exp => "exp",
+                    _ => "√"
+                };
+                write!(f, "{}(", name)?;
+                a.write(f, 0)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+struct Wrapper<'a>(&'a Expression, u8);
+
+impl fmt::Display for Wrapper<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.write(f, self.1)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+fn integer_digits(expression: &Expression) -> Option<(bool, String)> {
+    match expression {
+        Expression::Number(x) if x.fract() == 0.0 && *x < 1e15 => Some((false, format!("{}", x))),
+        Expression::Neg(a) => match a.as_ref() {
+            Expression::Number(x) if x.fract() == 0.0 && *x < 1e15 => Some((true, format!("{}", x))),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+fn superscript(expression: &Expression) -> Option<String> {
+    let (negative, digits) = integer_digits(expression)?;
+    Some(to_superscript(&format!("{}{}", if negative { "-" } else { "" }, digits)))
+}
+
+fn subscript(expression: &Expression) -> Option<String> {
+    let (negative, digits) = integer_digits(expression)?;
+    if negative {
+        return None;
+    }
+    Some(to_subscript(&digits))
+}
+
+// Writes the digits and minus signs of a formatted integer as superscripts.
+pub fn to_superscript(s: &str) -> String {
+    const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    s.chars().map(|c| if c == '-' { '⁻' } else { DIGITS[c.to_digit(10).unwrap() as usize] }).collect()
+}
+
+pub fn to_subscript(s: &str) -> String {
+    const DIGITS: [char; 10] = ['₀', '₁', '₂', '₃', '₄', '₅', '₆', '₇', '₈', '₉'];
+    s.chars().map(|c| DIGITS[c.to_digit(10).unwrap() as usize]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_evaluates_to(s: &str, expected: Complex<f64>) {
+        let actual = parse(s).unwrap().evaluate();
+        assert!((actual - expected).norm() < 1e-12, "{} evaluated to {}", s, actual);
+    }
+
+    fn display(s: &str) -> String {
+        parse(s).unwrap().to_string()
+    }
+
+    #[test]
+    fn expressions_evaluate() {
+        assert_evaluates_to("-1", Complex::new(-1.0, 0.0));
+        assert_evaluates_to("2/3", Complex::new(2.0 / 3.0, 0.0));
+        assert_evaluates_to("3+0.1i", Complex::new(3.0, 0.1));
+        assert_evaluates_to("exp(2πi/5)", Complex::from_polar(1.0, 2.0 * PI / 5.0));
+        assert_evaluates_to("exp(2*pi*i/5)", Complex::from_polar(1.0, 2.0 * PI / 5.0));
+        assert_evaluates_to("zeta(5)^2", Complex::from_polar(1.0, 4.0 * PI / 5.0));
+        assert_evaluates_to("ζ3", Complex::from_polar(1.0, 2.0 * PI / 3.0));
+        assert_evaluates_to("sqrt(-4)", Complex::new(0.0, 2.0));
+        assert_evaluates_to("√2", Complex::new(2f64.sqrt(), 0.0));
+        assert_evaluates_to("-2^2", Complex::new(-4.0, 0.0));
+        assert_evaluates_to("2^-1", Complex::new(0.5, 0.0));
+        assert_evaluates_to("(1 + i)(1 − i)", Complex::new(2.0, 0.0));
+        assert_evaluates_to("e^(i pi)", Complex::new(-1.0, 0.0));
+        assert_evaluates_to("1e-3", Complex::new(1e-3, 0.0));
+        assert_evaluates_to("2.9e0", Complex::new(2.9, 0.0));
+        assert_evaluates_to("3 + 1e-3i", Complex::new(3.0, 1e-3));
+        assert_evaluates_to("1E+2", Complex::new(100.0, 0.0));
+        assert_evaluates_to("2e", Complex::new(2.0 * std::f64::consts::E, 0.0));
+        assert_evaluates_to("2e - 3", Complex::new(2.0 * std::f64::consts::E - 3.0, 0.0));
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        assert!(parse("").is_err());
+        assert!(parse("q").is_err());
+        assert!(parse("exp(2").is_err());
+        assert!(parse("1 +").is_err());
+        assert!(parse("1)").is_err());
+        assert!(parse("1 $ 2").is_err());
+    }
+
+    #[test]
+    fn expressions_are_displayed() {
+        assert_eq!(display("1"), "1");
+        assert_eq!(display("-1"), "−1");
+        assert_eq!(display("i"), "𝑖");
+        assert_eq!(display("exp(2πi/3)"), "exp(2π𝑖/3)");
+        assert_eq!(display("exp(2*pi*i/5)"), "exp(2π𝑖/5)");
+        assert_eq!(display("3+0.1i"), "3 + 0.1𝑖");
+        assert_eq!(display("2.9"), "2.9");
+        assert_eq!(display("2*3"), "2·3");
+        assert_eq!(display("zeta(5)^2"), "ζ₅²");
+        assert_eq!(display("sqrt(2)"), "√2");
+        assert_eq!(display("sqrt(2+i)"), "√(2 + 𝑖)");
+        assert_eq!(display("1 - (2 - 3)"), "1 − (2 − 3)");
+        assert_eq!(display("2^-1"), "2⁻¹");
+    }
+}
@@ -4,7 +4,7 @@ use num::complex::Complex;
 
 #[repr(u8)]
 #[wasm_bindgen]
-#[derive(PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub enum Direction {
     North,
     South,
@@ -12,6 +12,76 @@ pub enum Direction {
     West
 }
 
+impl Direction {
+    pub fn inverse(&self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East
+        }
+    }
+
+    pub fn letter(&self) -> char {
+        match self {
+            Direction::North => 'N',
+            Direction::South => 'S',
+            Direction::East => 'E',
+            Direction::West => 'W'
+        }
+    }
+
+    pub fn from_letter(c: char) -> Option<Direction> {
+        match c.to_ascii_uppercase() {
+            'N' => Some(Direction::North),
+            'S' => Some(Direction::South),
+            'E' => Some(Direction::East),
+            'W' => Some(Direction::West),
+            _ => None
+        }
+    }
+}
+
+// Entries of the generator matrices as Laurent polynomials in q, each given by
+// a list of (exponent, coefficient) pairs.
+pub type Polynomial = &'static [(i32, i32)];
+
+pub fn generator_polynomials(direction: &Direction) -> [[Polynomial; 3]; 3] {
+    // See https://arxiv.org/abs/1904.11730v3
+    match direction {
+        Direction::North => [
+            [&[], &[], &[(-1, -1)]],
+            [&[], &[(1, -1)], &[(-1, -1), (1, 1)]],
+            [&[(0, -1)], &[], &[(-1, -1), (0, 1)]]
+        ],
+        Direction::South => [
+            [&[(0, 1), (1, -1)], &[], &[(0, -1)]],
+            [&[(-1, 1), (1, -1)], &[(-1, -1)], &[]],
+            [&[(1, -1)], &[], &[]]
+        ],
+        Direction::East => [
+            [&[(-1, -1)], &[(0, 1)], &[]],
+            [&[], &[(0, 1)], &[]],
+            [&[], &[(0, 1)], &[(1, -1)]]
+        ],
+        Direction::West => [
+            [&[(1, -1)], &[(1, 1)], &[]],
+            [&[], &[(0, 1)], &[]],
+            [&[], &[(-1, 1)], &[(-1, -1)]]
+        ]
+    }
+}
+
+pub fn generator_matrix(direction: &Direction, q: &Complex<f64>) -> Matrix {
+    let mut res = Matrix::zero();
+    for (i, row) in generator_polynomials(direction).iter().enumerate() {
+        for (j, polynomial) in row.iter().enumerate() {
+            res.d[i][j] = evaluate_polynomial(polynomial, q);
+        }
+    }
+    res
+}
+
 pub struct Group {
     north_matrix: Matrix,
     south_matrix: Matrix,
@@ -22,55 +92,42 @@ pub struct Group {
 
 impl Group {
     pub fn new(q: &Complex<f64>) -> Self {
-        // See https://arxiv.org/abs/1904.11730v3
-        let mut north_matrix = Matrix::zero();
-        north_matrix.d[0][2] = evaluate_polynomial(&[(-1, -1)], &q);
-        north_matrix.d[1][1] = evaluate_polynomial(&[(1, -1)], &q);
-        north_matrix.d[1][2] = evaluate_polynomial(&[(-1, -1), (1, 1)], &q);
-        north_matrix.d[2][0] = evaluate_polynomial(&[(0, -1)], &q);
-        north_matrix.d[2][2] = evaluate_polynomial(&[(-1, -1), (0, 1)], &q);
-
-        let mut south_matrix = Matrix::zero();
-        south_matrix.d[0][0] = evaluate_polynomial(&[(0, 1), (1, -1)], &q);
-        south_matrix.d[0][2] = evaluate_polynomial(&[(0, -1)], &q);
-        south_matrix.d[1][0] = evaluate_polynomial(&[(-1, 1), (1, -1)], &q);
-        south_matrix.d[1][1] = evaluate_polynomial(&[(-1, -1)], &q);
-        south_matrix.d[2][0] = evaluate_polynomial(&[(1, -1)], &q);
-
-        let mut east_matrix = Matrix::zero();
-        east_matrix.d[0][0] = evaluate_polynomial(&[(-1, -1)], &q);
-        east_matrix.d[0][1] = evaluate_polynomial(&[(0, 1)], &q);
-        east_matrix.d[1][1] = evaluate_polynomial(&[(0, 1)], &q);
-        east_matrix.d[2][1] = evaluate_polynomial(&[(0, 1)], &q);
-        east_matrix.d[2][2] = evaluate_polynomial(&[(1, -1)], &q);
-
-        let mut west_matrix = Matrix::zero();
-        west_matrix.d[0][0] = evaluate_polynomial(&[(1, -1)], &q);
-        west_matrix.d[0][1] = evaluate_polynomial(&[(1, 1)], &q);
-        west_matrix.d[1][1] = evaluate_polynomial(&[(0, 1)], &q);
-        west_matrix.d[2][1] = evaluate_polynomial(&[(-1, 1)], &q);
-        west_matrix.d[2][2] = evaluate_polynomial(&[(-1, -1)], &q);
-
+        let north_matrix = generator_matrix(&Direction::North, q);
+        let south_matrix = generator_matrix(&Direction::South, q);
+        let east_matrix = generator_matrix(&Direction::East, q);
+        let west_matrix = generator_matrix(&Direction::West, q);
         let current_matrix = Matrix::identity();
         Self { north_matrix, south_matrix, east_matrix, west_matrix, current_matrix }
     }
 
     pub fn push(&mut self, direction: &Direction) {
-        let matrix = match direction {
+        self.current_matrix = &self.current_matrix * self.generator(direction);
+    }
+
+    pub fn generator(&self, direction: &Direction) -> &Matrix {
+        match direction {
             Direction::North => &self.north_matrix,
             Direction::South => &self.south_matrix,
             Direction::East => &self.east_matrix,
             Direction::West => &self.west_matrix
-        };
-        self.current_matrix = &self.current_matrix * matrix;
+        }
+    }
+
+    pub fn current(&self) -> &Matrix {
+        &self.current_matrix
     }
 
-    pub fn current_is_identity(&self) -> bool {
-        self.current_matrix == Matrix::identity()
+    // Divides the current matrix by its Frobenius norm and returns the
+    // logarithm of the norm, so that long products neither overflow nor
+    // underflow.
+    pub fn renormalise(&mut self) -> f64 {
+        let norm = self.current_matrix.frobenius_norm();
+        self.current_matrix = self.current_matrix.scale(Complex::new(1.0 / norm, 0.0));
+        norm.ln()
     }
 
-    pub fn distance_from_identity(&self) -> f64 {
-        self.current_matrix.distance_from_identity()
+    pub fn current_is_identity(&self, tolerance: f64) -> bool {
+        self.current_matrix.approx_eq(&Matrix::identity(), tolerance)
     }
 
     pub fn flatten(&self) -> [Complex<f64>; 9] {
@@ -87,7 +144,7 @@ mod tests {
     fn group_starts_at_identity() {
         let q = Complex::new(60.0, 42.0);
         let group = Group::new(&q);
-        assert!(group.current_is_identity());
+        assert!(group.current_is_identity(1e-10));
     }
 
     #[test]
@@ -95,7 +152,27 @@ mod tests {
         let q = Complex::new(60.0, 42.0);
         let mut group = Group::new(&q);
         group.push(&Direction::North);
-        assert!(!group.current_is_identity());
+        assert!(!group.current_is_identity(1e-10));
+    }
+
+    #[test]
+    fn generators_are_evaluated_at_q() {
+        let q = Complex::new(60.0, 42.0);
+        let group = Group::new(&q);
+        assert_eq!(group.generator(&Direction::West), &generator_matrix(&Direction::West, &q));
+        assert_eq!(group.generator(&Direction::West).d[0][1], q);
+    }
+
+    #[test]
+    fn renormalising_keeps_direction_of_matrix() {
+        let q = Complex::new(3.0, 0.0);
+        let mut group = Group::new(&q);
+        group.push(&Direction::East);
+        let east = group.current().scale(Complex::new(1.0, 0.0));
+        let log_norm = group.renormalise();
+        assert!((log_norm - east.frobenius_norm().ln()).abs() < 1e-12);
+        assert!((group.current().frobenius_norm() - 1.0).abs() < 1e-12);
+        assert_eq!(group.current().scale(Complex::new(log_norm.exp(), 0.0)), east);
     }
 
     #[test]
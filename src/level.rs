@@ -1,11 +1,182 @@
+use crate::algebra::{Matrix, Metric};
+use crate::braid;
+use crate::exact::{self, ExactValue, LaurentMatrix};
 use crate::group::{Direction, Group};
+use crate::render::{self, Notation};
+use crate::search;
+use crate::word;
 use num::Complex;
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+
+#[repr(u8)]
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SolutionClass {
+    // The word reduces to the empty word in the free group. Words in a level
+    // are kept reduced, so only words given to `classify` directly end up
+    // here.
+    FreelyTrivial,
+    // The word is non-trivial in the free group. As North, South, East and
+    // West map into a free subgroup of the braid group, see `braid`, its braid
+    // is then non-trivial too, so the word is a genuine element of the kernel
+    // of the representation.
+    KernelElement
+}
+
+pub fn classify(word: &[Direction]) -> SolutionClass {
+    if word::free_reduce(word).is_empty() {
+        SolutionClass::FreelyTrivial
+    } else {
+        SolutionClass::KernelElement
+    }
+}
+
+#[repr(u8)]
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Goal {
+    // Every matrix must become the identity.
+    Identity,
+    // Every matrix need only become a multiple of the identity, which makes
+    // the word a kernel element of the projectivised representation.
+    Scalar
+}
+
+impl Goal {
+    pub fn is_reached(&self, matrix: &Matrix, tolerance: f64) -> bool {
+        match self {
+            Goal::Identity => matrix.approx_eq(&Matrix::identity(), tolerance),
+            Goal::Scalar => matrix.is_scalar(tolerance)
+        }
+    }
+
+    // For scalar goals, the distance is measured from the closest scalar
+    // matrix. Apart from the Frobenius distance, it is taken relative to the
+    // Frobenius norm of the matrix like the projective metric, as dividing by
+    // the scalar itself blows up when the trace is small.
+    pub fn distance(&self, matrix: &Matrix, metric: Metric) -> f64 {
+        match (self, metric) {
+            (Goal::Identity, _) => matrix.distance(metric),
+            (Goal::Scalar, Metric::Frobenius) => matrix.distance_from_scalar(),
+            (Goal::Scalar, Metric::Operator | Metric::LogMaxEntry) => {
+                let norm = matrix.frobenius_norm();
+                if norm == 0.0 {
+                    return 0.0;
+                }
+                let difference = matrix.minus_scalar().scale(Complex::new(1.0 / norm, 0.0));
+                match metric {
+                    Metric::Operator => difference.operator_norm(),
+                    _ => difference.max_entry().ln_1p()
+                }
+            }
+            (Goal::Scalar, Metric::LogSpectralRadius | Metric::Projective) => matrix.distance(metric)
+        }
+    }
+}
+
+pub const DEFAULT_TOLERANCE: f64 = 1e-10;
+
+// Only roots of unity and ±1 are written exactly, since the entries at other
+// integers quickly grow into unreadably long rationals.
+fn exact_value(q: &Complex<f64>) -> Option<ExactValue> {
+    exact::recognise(q).filter(|value| !matches!(value, ExactValue::Integer(n) if n.abs() != 1))
+}
+
+// Writes the entries of a matrix at q exactly if possible, and otherwise as the
+// given floating point entries.
+fn write_entries(q: &Complex<f64>, symbolic: Option<&LaurentMatrix>, flattened: &[Complex<f64>; 9], notation: Notation) -> Vec<String> {
+    match (exact_value(q), symbolic) {
+        (Some(q), Some(symbolic)) => symbolic.d.iter().flatten().map(|p| p.evaluate(q, notation)).collect(),
+        // Only show imaginary part if 𝑞 itself is non-real.
+        _ => flattened.iter().map(|z| render::complex(z, q.im != 0.0, notation)).collect()
+    }
+}
+
+// Three stars for an optimal solution, two for one at most twice as long as
+// the optimum, and one for any other solution.
+pub fn rate(length: usize, optimal_length: usize) -> u8 {
+    if length <= optimal_length {
+        3
+    } else if length <= 2 * optimal_length {
+        2
+    } else {
+        1
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Solution {
+    pub word: Vec<Direction>,
+    // Milliseconds since the Unix epoch.
+    pub found_at: f64,
+    // Distances from the identity for each value of q, which need not all
+    // vanish in levels where numerical errors are tolerated.
+    pub distances: Vec<f64>
+}
+
+fn now() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_millis() as f64)
+    }
+}
+
+// Statistics of the matrix at a single value of q after some prefix of the
+// word.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Snapshot {
+    // The Frobenius distance from the identity.
+    pub distance: f64,
+    pub max_entry: f64,
+    pub determinant: Complex<f64>
+}
+
+impl Snapshot {
+    fn of(matrix: &Matrix) -> Snapshot {
+        Snapshot {
+            distance: matrix.distance_from_identity(),
+            max_entry: matrix.max_entry(),
+            determinant: matrix.determinant()
+        }
+    }
+}
+
+// A word being played move by move, of which the first `position` moves have
+// been made.
+pub struct Replay {
+    pub word: Vec<Direction>,
+    pub position: usize
+}
 
 pub struct Level {
     pub qs: Vec<Complex<f64>>,
     pub groups: Vec<Group>,
     pub word: Vec<Direction>,
-    pub flattened: Vec<[Complex<f64>; 9]>
+    pub flattened: Vec<[Complex<f64>; 9]>,
+    // Snapshots of each matrix after every prefix of the word, starting with
+    // the empty word.
+    pub history: Vec<Vec<Snapshot>>,
+    // The products of the prefixes of the word for symbolic q. They are only
+    // multiplied out when asked for, one move at a time, and dropped again
+    // when moves are undone.
+    symbolic: RefCell<Vec<LaurentMatrix>>,
+    pub tolerance: f64,
+    pub goal: Goal,
+    pub metric: Metric,
+    pub completed: bool,
+    // Whether the current word is a solution the player was credited with,
+    // as opposed to one that was loaded or replayed.
+    pub credited: bool,
+    pub best: Option<Solution>,
+    pub optimal_length: Option<usize>,
+    pub solution: Option<Vec<Direction>>,
+    pub replay: Option<Replay>
 }
 
 impl Level {
@@ -14,11 +185,23 @@ impl Level {
         let flattened = groups.iter().map(
             |g| g.flatten()).collect();
         let word = vec![];
+        let history = vec![groups.iter().map(|g| Snapshot::of(g.current())).collect()];
         Level {
             qs,
             groups,
             word,
-            flattened
+            flattened,
+            history,
+            symbolic: RefCell::new(vec![LaurentMatrix::identity()]),
+            tolerance: DEFAULT_TOLERANCE,
+            goal: Goal::Identity,
+            metric: Metric::Frobenius,
+            completed: false,
+            credited: false,
+            best: None,
+            optimal_length: None,
+            solution: None,
+            replay: None
         }
     }
 
@@ -27,27 +210,168 @@ impl Level {
     }
 
     pub fn push(&mut self, direction: Direction) {
+        self.apply(direction);
+        if self.is_solved() {
+            self.record_solution();
+            self.credited = true;
+        }
+    }
+
+    // Makes a move without crediting the player with any solution it reaches,
+    // for words that were loaded or replayed rather than played.
+    fn apply(&mut self, direction: Direction) {
+        self.credited = false;
         for i in 0..self.groups.len() {
             self.groups[i].push(&direction);
         }
         let last_is_opposite = !self.word.is_empty() &&
-            self.word.last().unwrap() == &match direction {
-            Direction::North => Direction::South,
-            Direction::South => Direction::North,
-            Direction::East => Direction::West,
-            Direction::West => Direction::East
-        };
+            self.word.last().unwrap() == &direction.inverse();
         if last_is_opposite {
             self.word.pop();
+            self.history.pop();
+            self.symbolic.get_mut().truncate(self.word.len() + 1);
         } else {
             self.word.push(direction);
+            self.history.push(self.groups.iter().map(|g| Snapshot::of(g.current())).collect());
         }
         self.update_flattened();
     }
 
+    fn record_solution(&mut self) {
+        self.completed = true;
+        let solution = Solution {
+            word: self.word.clone(),
+            found_at: now(),
+            distances: self.distances()
+        };
+        self.offer_best(solution);
+    }
+
+    // Keeps the given solution if it is shorter than the best one so far.
+    pub fn offer_best(&mut self, solution: Solution) {
+        if self.best.as_ref().is_none_or(|best| solution.word.len() < best.word.len()) {
+            self.best = Some(solution);
+        }
+    }
+
+    // The distances of the matrices from the goal of the level.
+    pub fn distances(&self) -> Vec<f64> {
+        self.groups.iter().map(|g| self.goal.distance(g.current(), self.metric)).collect()
+    }
+
+    // The distance from the identity, the largest modulus of an entry and the
+    // real and imaginary parts of the determinant of each matrix, for every
+    // prefix of the word from the empty one up.
+    pub fn history_values(&self) -> Vec<f64> {
+        self.history.iter().flatten()
+            .flat_map(|s| [s.distance, s.max_entry, s.determinant.re, s.determinant.im])
+            .collect()
+    }
+
+    // The real and imaginary parts of the entries of each matrix, in row-major
+    // order.
+    pub fn entries(&self) -> Vec<f64> {
+        self.flattened.iter().flatten().flat_map(|z| [z.re, z.im]).collect()
+    }
+
+    // The product of the word for symbolic q.
+    pub fn symbolic(&self) -> LaurentMatrix {
+        let mut products = self.symbolic.borrow_mut();
+        while products.len() <= self.word.len() {
+            let next = products.last().unwrap() * &LaurentMatrix::generator(&self.word[products.len() - 1]);
+            products.push(next);
+        }
+        products[self.word.len()].clone()
+    }
+
+    // The entries of the matrix for symbolic q, in row-major order.
+    pub fn polynomials(&self, notation: Notation) -> Vec<String> {
+        self.symbolic().write(notation)
+    }
+
+    // The entries of each matrix, written exactly for those values of q where
+    // this is possible and as floating point numbers otherwise.
+    pub fn entries_written(&self, notation: Notation) -> Vec<Vec<String>> {
+        let symbolic = self.qs.iter().any(|q| exact_value(q).is_some()).then(|| self.symbolic());
+        self.qs.iter().zip(&self.flattened)
+            .map(|(q, flattened)| write_entries(q, symbolic.as_ref(), flattened, notation))
+            .collect()
+    }
+
+    pub fn generator_entries_written(&self, direction: &Direction, notation: Notation) -> Vec<Vec<String>> {
+        let symbolic = LaurentMatrix::generator(direction);
+        self.qs.iter().zip(&self.groups)
+            .map(|(q, group)| write_entries(q, Some(&symbolic), &group.generator(direction).flatten(), notation))
+            .collect()
+    }
+
+    // Applies `f` to the current matrix for each value of q and lists the real
+    // and imaginary parts of the results.
+    pub fn current_values<F, I>(&self, f: F) -> Vec<f64>
+        where F: Fn(&Matrix) -> I, I: IntoIterator<Item = Complex<f64>> {
+        self.groups.iter().flat_map(|g| f(g.current())).flat_map(|z| [z.re, z.im]).collect()
+    }
+
+    // The real and imaginary parts of the entries of the generator matrix for
+    // each value of q, in the layout of `entries`.
+    pub fn generator_entries(&self, direction: &Direction) -> Vec<f64> {
+        self.groups.iter().flat_map(|g| g.generator(direction).flatten()).flat_map(|z| [z.re, z.im]).collect()
+    }
+
+    pub fn distances_for(&self, word: &[Direction]) -> Vec<f64> {
+        let mut groups = Self::make_groups(&self.qs);
+        for group in groups.iter_mut() {
+            for direction in word {
+                group.push(direction);
+            }
+        }
+        groups.iter().map(|g| self.goal.distance(g.current(), self.metric)).collect()
+    }
+
+    pub fn set_word(&mut self, word: &[Direction]) {
+        self.reset();
+        for direction in word {
+            self.apply(*direction);
+        }
+    }
+
+    // Resets the level so that the given word can be played with
+    // `step_forward` and `step_back`.
+    pub fn start_replay(&mut self, word: Vec<Direction>) {
+        self.reset();
+        self.replay = Some(Replay { word, position: 0 });
+    }
+
+    pub fn step_forward(&mut self) -> bool {
+        let direction = match &mut self.replay {
+            Some(replay) if replay.position < replay.word.len() => {
+                replay.position += 1;
+                replay.word[replay.position - 1]
+            }
+            _ => return false
+        };
+        self.apply(direction);
+        true
+    }
+
+    pub fn step_back(&mut self) -> bool {
+        let direction = match &mut self.replay {
+            Some(replay) if replay.position > 0 => {
+                replay.position -= 1;
+                replay.word[replay.position]
+            }
+            _ => return false
+        };
+        self.apply(direction.inverse());
+        true
+    }
+
     pub fn reset(&mut self) {
         self.groups = Self::make_groups(&self.qs);
         self.word = vec![];
+        self.credited = false;
+        self.history.truncate(1);
+        self.symbolic.get_mut().truncate(1);
         self.update_flattened();
     }
 
@@ -56,16 +380,76 @@ impl Level {
     }
 
     pub fn word(&self) -> String {
-        self.word.iter().map(|d| match d {
-            Direction::North => 'N',
-            Direction::South => 'S',
-            Direction::East => 'E',
-            Direction::West => 'W',
-        }).collect::<String>()
+        word::word_to_string(&self.word)
+    }
+
+    pub fn canonical_word(&self) -> String {
+        word::word_to_string(&word::canonical_form(&self.word))
+    }
+
+    pub fn braid(&self) -> Vec<i32> {
+        braid::word_to_braid(&self.word)
     }
 
     pub fn is_solved(&self) -> bool {
-        !self.word.is_empty() && self.groups.iter().all(|g| g.current_is_identity())
+        !self.word.is_empty() && self.groups.iter().all(|g| self.goal.is_reached(g.current(), self.tolerance))
+    }
+
+    // Whether the word would solve the level if the goal were only to reach
+    // scalar matrices.
+    pub fn is_solved_up_to_scalar(&self) -> bool {
+        !self.word.is_empty() && self.groups.iter().all(|g| Goal::Scalar.is_reached(g.current(), self.tolerance))
+    }
+
+    // A solution whose braid is non-trivial is an element of the kernel of the
+    // Burau representation specialised at the level's values of q.
+    pub fn is_burau_kernel_element(&self) -> bool {
+        self.solution_class() == Some(SolutionClass::KernelElement)
+    }
+
+    pub fn solution_class(&self) -> Option<SolutionClass> {
+        if !self.is_solved() {
+            return None;
+        }
+        // Handle reduction decides triviality of the braid independently of
+        // the matrices and of the free group, so it double checks both.
+        let class = classify(&self.word);
+        let braid_is_trivial = braid::is_trivial(&self.braid());
+        debug_assert_eq!(braid_is_trivial, class == SolutionClass::FreelyTrivial,
+                         "handle reduction disagrees with free reduction for {}", self.word());
+        Some(class)
+    }
+
+    // The next move of a solution extending the current word. The rotations
+    // of the level's known solution and of its inverse solve the level too,
+    // though they are only known to be shortest when the optimal length of
+    // the level is given. Other words are extended by searching all sequences
+    // of at most `max_length` moves for a shortest completion.
+    pub fn hint(&self, max_length: usize) -> Option<Direction> {
+        if self.is_solved() {
+            return None;
+        }
+        if let Some(solution) = &self.solution {
+            let inverse: Vec<Direction> = solution.iter().rev().map(Direction::inverse).collect();
+            for known in [solution, &inverse] {
+                for i in 0..known.len() {
+                    let rotation = [&known[i..], &known[..i]].concat();
+                    if rotation.len() > self.word.len() && rotation.starts_with(&self.word) {
+                        return Some(rotation[self.word.len()]);
+                    }
+                }
+            }
+        }
+        search::shortest_completion(&self.qs, &self.word, max_length, self.tolerance, self.goal).map(|moves| moves[0])
+    }
+
+    // The rating of the current word, if it is a solution and the optimal
+    // length of the level is known.
+    pub fn rating(&self) -> Option<u8> {
+        if !self.is_solved() {
+            return None;
+        }
+        self.optimal_length.map(|optimal_length| rate(self.word.len(), optimal_length))
     }
 }
 
@@ -103,15 +487,92 @@ mod tests {
         let q = Complex::new(60.0, 42.0);
         let mut level = Level::new(vec![q]);
 
-        assert!(level.groups[0].current_is_identity());
+        assert!(level.groups[0].current_is_identity(DEFAULT_TOLERANCE));
 
         level.push(Direction::North);
         assert!(!level.word.is_empty());
-        assert!(!level.groups[0].current_is_identity());
+        assert!(!level.groups[0].current_is_identity(DEFAULT_TOLERANCE));
 
         level.reset();
         assert!(level.word.is_empty());
-        assert!(level.groups[0].current_is_identity());
+        assert!(level.groups[0].current_is_identity(DEFAULT_TOLERANCE));
+    }
+
+    #[test]
+    fn level_entries_are_flattened() {
+        let q = Complex::new(0.0, 1.0);
+        let mut level = Level::new(vec![q, q]);
+        level.push(Direction::East);
+        let entries = level.entries();
+        assert_eq!(entries.len(), 36);
+        // The top left entry of East is -1/q.
+        assert_eq!(&entries[0..4], &[0.0, 1.0, 1.0, 0.0]);
+        assert_eq!(&entries[0..18], &entries[18..36]);
+        assert_eq!(level.distances().len(), 2);
+    }
+
+    #[test]
+    fn level_entries_are_exact() {
+        let mut level = Level::new(vec![Complex::new(0.0, 1.0), Complex::new(2.5, 0.5)]);
+        level.push(Direction::East);
+        level.push(Direction::East);
+        assert_eq!(level.polynomials(Notation::Unicode)[0..3], ["𝑞⁻²", "−𝑞⁻¹ + 1", "0"]);
+        assert_eq!(level.polynomials(Notation::Latex)[0..3], ["q^{-2}", "-q^{-1} + 1", "0"]);
+        let entries = level.entries_written(Notation::Unicode);
+        assert_eq!(entries[0][0..3], ["−1", "1 + 𝑖", "0"]);
+        assert_eq!(entries[1][1], "0.6153846153846154 + 0.07692307692307693𝑖");
+        level.reset();
+        assert_eq!(level.polynomials(Notation::Unicode)[0], "1");
+
+        // Integers other than ±1 are shown as floating point numbers.
+        let mut level = Level::new(vec![Complex::new(-1.0, 0.0), Complex::new(2.0, 0.0)]);
+        level.push(Direction::East);
+        let entries = level.entries_written(Notation::Unicode);
+        assert_eq!(entries[0][0], "1");
+        assert_eq!(entries[1][0], "-0.5");
+    }
+
+    #[test]
+    fn symbolic_products_follow_the_word() {
+        use Direction::*;
+        let mut level = Level::new(vec![Complex::new(0.0, 1.0)]);
+        let product = |word: &[Direction]| word.iter()
+            .fold(LaurentMatrix::identity(), |product, direction| &product * &LaurentMatrix::generator(direction));
+        for direction in [East, North, North, West] {
+            level.push(direction);
+        }
+        assert_eq!(level.symbolic(), product(&[East, North, North, West]));
+        level.push(East);
+        level.push(South);
+        assert_eq!(level.symbolic(), product(&[East, North]));
+        level.push(West);
+        assert_eq!(level.symbolic(), product(&[East, North, West]));
+        level.reset();
+        assert_eq!(level.symbolic(), LaurentMatrix::identity());
+    }
+
+    #[test]
+    fn level_generator_entries_are_evaluated() {
+        let level = Level::new(vec![Complex::new(0.0, 1.0), Complex::new(2.5, 0.0)]);
+        let entries = level.generator_entries_written(&Direction::East, Notation::Unicode);
+        assert_eq!(entries[0], ["𝑖", "1", "0", "0", "1", "0", "0", "1", "−𝑖"]);
+        assert_eq!(entries[1][0], "-0.4");
+        let values = level.generator_entries(&Direction::East);
+        assert_eq!(values.len(), 36);
+        assert_eq!(&values[0..2], &[0.0, 1.0]);
+        assert_eq!(values[18], -0.4);
+    }
+
+    #[test]
+    fn level_current_values_apply_to_each_matrix() {
+        let mut level = Level::new(vec![Complex::new(2.0, 0.0), Complex::new(0.0, 1.0)]);
+        assert_eq!(level.current_values(|m| [m.trace()]), vec![3.0, 0.0, 3.0, 0.0]);
+        level.push(Direction::North);
+        level.push(Direction::East);
+        // Every move is a braid with exponent sum zero, so determinants stay 1.
+        let determinants = level.current_values(|m| [m.determinant()]);
+        assert!(determinants.chunks(2).all(|z| (z[0] - 1.0).abs() < 1e-12 && z[1].abs() < 1e-12));
+        assert_eq!(level.current_values(|m| m.eigenvalues()).len(), 12);
     }
 
     #[test]
@@ -125,4 +586,265 @@ mod tests {
         level.push(Direction::North);
         assert!(level.is_solved());
     }
+
+    #[test]
+    fn level_can_be_solved_up_to_scalar() {
+        use Direction::*;
+        let q = Complex::from_polar(1.0, std::f64::consts::PI / 3.0);
+        let mut level = Level::new(vec![q]);
+        level.set_word(&[North, East, South]);
+        assert!(!level.is_solved_up_to_scalar());
+        assert!(level.distances()[0] > 0.0);
+        level.push(West);
+        assert!(level.is_solved_up_to_scalar());
+        assert!(!level.is_solved());
+        assert!(!level.completed);
+
+        level.goal = Goal::Scalar;
+        assert!(level.is_solved());
+        assert!(level.distances()[0] < 1e-10);
+        level.reset();
+        assert_eq!(level.hint(4), Some(North));
+    }
+
+    #[test]
+    fn scalar_goal_distances_stay_bounded() {
+        let omega = Complex::from_polar(1.0, 2.0 * std::f64::consts::PI / 3.0);
+        let mut matrix = Matrix::identity().scale(omega);
+        for metric in [Metric::Frobenius, Metric::Operator, Metric::LogMaxEntry, Metric::Projective] {
+            assert!(Goal::Scalar.distance(&matrix, metric) < 1e-10);
+        }
+
+        // A matrix with trace close to zero.
+        matrix.d[1][1] = Complex::new(1.0, 0.0);
+        matrix.d[2][2] = omega * omega + 1e-12;
+        for metric in [Metric::Operator, Metric::LogMaxEntry, Metric::Projective] {
+            let distance = Goal::Scalar.distance(&matrix, metric);
+            assert!(distance > 0.1 && distance <= 1.0);
+        }
+    }
+
+    #[test]
+    fn level_records_history_of_prefixes() {
+        use Direction::*;
+        let mut level = Level::new(vec![Complex::new(1.0, 0.0), Complex::new(3.0, 0.0)]);
+        assert_eq!(level.history_values(), vec![0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0]);
+        level.set_word(&[East, East, North]);
+        assert_eq!(level.history.len(), 4);
+        assert_eq!(level.history_values().len(), 4 * 2 * 4);
+        assert!(level.history[3].iter().all(|s| (s.determinant - Complex::new(1.0, 0.0)).norm() < 1e-10));
+        assert!(level.history[2][1].max_entry > level.history[1][1].max_entry);
+        assert_eq!(level.history[3][1].distance, level.groups[1].current().distance_from_identity());
+
+        // Cancelling a move forgets its snapshot, so the history stays in step
+        // with the word.
+        let before = level.history[..3].to_vec();
+        level.push(South);
+        assert_eq!(level.history, before);
+        level.reset();
+        assert_eq!(level.history.len(), 1);
+    }
+
+    #[test]
+    fn overflowed_words_are_not_solutions() {
+        let mut level = Level::new(vec![Complex::new(3.0, 0.0)]);
+        level.set_word(&[Direction::East; 1000]);
+        assert!(level.groups[0].current().d.iter().flatten().any(|z| !z.is_finite()));
+        assert!(!level.is_solved());
+        assert!(!level.completed);
+        assert_eq!(level.best, None);
+    }
+
+    #[test]
+    fn long_words_are_far_from_the_identity_in_every_metric() {
+        use crate::lyapunov::Rng;
+        let directions = [Direction::North, Direction::South, Direction::East, Direction::West];
+        let mut rng = Rng::new(1);
+        let mut level = Level::new(vec![Complex::new(3.0, 0.0)]);
+        while level.word.len() < 200 {
+            level.push(directions[rng.below(4) as usize]);
+        }
+        for metric in [Metric::Frobenius, Metric::Operator, Metric::LogMaxEntry, Metric::LogSpectralRadius] {
+            level.metric = metric;
+            assert!(level.distances()[0] > 10.0, "{:?}", metric);
+        }
+    }
+
+    #[test]
+    fn level_distances_use_metric() {
+        let mut level = Level::new(vec![Complex::new(3.0, 0.0)]);
+        level.set_word(&[Direction::East; 20]);
+        let frobenius = level.distances()[0];
+        level.metric = Metric::LogMaxEntry;
+        let log_max_entry = level.distances()[0];
+        assert!(frobenius > 1e9);
+        assert!(log_max_entry > 0.0 && log_max_entry < 50.0);
+        level.metric = Metric::Projective;
+        assert!(level.distances()[0] < 2.0);
+    }
+
+    fn play(level: &mut Level, word: &[Direction]) {
+        level.reset();
+        for direction in word {
+            level.push(*direction);
+        }
+    }
+
+    #[test]
+    fn loaded_and_replayed_words_are_not_credited() {
+        use Direction::*;
+        let mut level = Level::new(vec![Complex::new(1.0, 0.0)]);
+        level.set_word(&[North, North]);
+        assert!(level.is_solved());
+        assert!(!level.completed);
+        assert!(!level.credited);
+        assert_eq!(level.best, None);
+
+        level.start_replay(vec![South, South]);
+        while level.step_forward() {}
+        assert!(level.is_solved());
+        level.step_back();
+        level.step_forward();
+        assert!(!level.completed);
+        assert_eq!(level.best, None);
+
+        play(&mut level, &[North, North]);
+        assert!(level.completed);
+        assert!(level.credited);
+        assert_eq!(level.best.as_ref().unwrap().word, vec![North, North]);
+    }
+
+    #[test]
+    fn level_remembers_shortest_solution() {
+        use Direction::*;
+        let q = Complex::new(1.0, 0.0);
+        let mut level = Level::new(vec![q]);
+        assert!(!level.completed);
+
+        play(&mut level, &[East, North, North, West]);
+        assert!(level.completed);
+        assert_eq!(level.best.as_ref().unwrap().word, vec![East, North, North, West]);
+
+        play(&mut level, &[North, North]);
+        let found_at = level.best.as_ref().unwrap().found_at;
+        play(&mut level, &[South, South, South, South]);
+        let best = level.best.as_ref().unwrap();
+        assert_eq!(best.word, vec![North, North]);
+        assert_eq!(best.found_at, found_at);
+        assert!(found_at > 0.0);
+        assert_eq!(best.distances.len(), 1);
+        assert!(best.distances[0] < 1e-10);
+
+        level.reset();
+        assert!(level.completed);
+    }
+
+    #[test]
+    fn level_rates_solutions_against_optimum() {
+        use Direction::*;
+        let q = Complex::new(1.0, 0.0);
+        let mut level = Level::new(vec![q]);
+        level.set_word(&[North, North]);
+        assert_eq!(level.rating(), None);
+
+        level.optimal_length = Some(2);
+        assert_eq!(level.rating(), Some(3));
+        level.set_word(&[East, North, North, West]);
+        assert_eq!(level.rating(), Some(2));
+        level.set_word(&[East, East, North, North, West, West]);
+        assert_eq!(level.rating(), Some(1));
+        level.push(North);
+        assert_eq!(level.rating(), None);
+    }
+
+    #[test]
+    fn level_hints_lead_to_shortest_solution() {
+        use Direction::*;
+        let q = Complex::new(-1.0, 0.0);
+        let mut level = Level::new(vec![q]);
+        level.solution = Some(vec![South, West, North, East]);
+        assert_eq!(level.hint(0), Some(South));
+        level.set_word(&[North, East]);
+        assert_eq!(level.hint(0), Some(South));
+        level.set_word(&[North, North]);
+        assert_eq!(level.hint(0), None);
+        assert_eq!(level.hint(4), Some(South));
+
+        while let Some(direction) = level.hint(6) {
+            level.push(direction);
+        }
+        assert!(level.is_solved());
+        assert_eq!(level.word.len(), 4);
+    }
+
+    #[test]
+    fn replay_steps_through_word() {
+        use Direction::*;
+        let q = Complex::new(-1.0, 0.0);
+        let mut level = Level::new(vec![q]);
+        assert!(!level.step_forward());
+
+        level.start_replay(vec![South, West, North, East]);
+        assert!(!level.step_back());
+        assert!(level.step_forward());
+        assert!(level.step_forward());
+        assert_eq!(level.word(), "SW");
+        assert!(level.step_back());
+        assert_eq!(level.word(), "S");
+        while level.step_forward() {}
+        assert_eq!(level.word(), "SWNE");
+        assert!(level.is_solved());
+        while level.step_back() {}
+        assert!(level.word.is_empty());
+        assert!(level.groups[0].current_is_identity(DEFAULT_TOLERANCE));
+    }
+
+    #[test]
+    fn classify_recognises_trivial_words() {
+        use Direction::*;
+        assert_eq!(classify(&[North, East, West, South]), SolutionClass::FreelyTrivial);
+        assert_eq!(classify(&[North, East, South, West]), SolutionClass::KernelElement);
+        assert_eq!(classify(&[North, North]), SolutionClass::KernelElement);
+    }
+
+    #[test]
+    fn reduced_words_have_non_trivial_braids() {
+        let directions = [Direction::North, Direction::South, Direction::East, Direction::West];
+        let mut words: Vec<Vec<Direction>> = vec![vec![]];
+        for _ in 0..4 {
+            words = words.iter().flat_map(|w| directions.iter().filter_map(move |d| {
+                if w.last() == Some(&d.inverse()) {
+                    return None;
+                }
+                let mut extended = w.clone();
+                extended.push(*d);
+                Some(extended)
+            })).collect();
+            for w in &words {
+                assert_eq!(classify(w), SolutionClass::KernelElement);
+                assert!(!braid::is_trivial(&braid::word_to_braid(w)));
+            }
+        }
+    }
+
+    #[test]
+    fn level_solution_is_burau_kernel_element() {
+        let q = Complex::new(1.0, 0.0);
+        let mut level = Level::new(vec![q]);
+
+        assert!(!level.is_burau_kernel_element());
+        level.push(Direction::North);
+        level.push(Direction::North);
+        assert!(level.is_burau_kernel_element());
+        assert!(!braid::is_trivial(&level.braid()));
+
+        // The long solution of the seventh level is checked by handle
+        // reduction too.
+        let omega = |n: f64| Complex::from_polar(1.0, 2.0 * std::f64::consts::PI / n);
+        let mut level = Level::new(vec![omega(3.0), omega(5.0)]);
+        for _ in 0..30 {
+            level.push(Direction::East);
+        }
+        assert_eq!(level.solution_class(), Some(SolutionClass::KernelElement));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,186 @@
+use num::Complex;
+use serde::Deserialize;
+
+use crate::expression;
+use crate::group::Direction;
+use crate::level::{Goal, DEFAULT_TOLERANCE};
+use crate::word;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawLevelPack {
+    levels: Vec<RawLevel>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawLevel {
+    title: String,
+    description: String,
+    qs: Vec<String>,
+    tolerance: Option<f64>,
+    representation: Option<String>,
+    goal: Option<String>,
+    optimal_length: Option<usize>,
+    solution: Option<String>
+}
+
+pub struct LevelDefinition {
+    pub title: String,
+    pub description: String,
+    pub qs: Vec<Complex<f64>>,
+    pub q_displays: Vec<String>,
+    pub tolerance: f64,
+    pub goal: Goal,
+    // Length of the shortest solution, where known.
+    pub optimal_length: Option<usize>,
+    // A known solution, used for giving hints. It is a shortest one when the
+    // optimal length is given and otherwise just one that is known to work.
+    pub solution: Option<Vec<Direction>>
+}
+
+// Level packs are JSON documents of the form
+//
+//     { "levels": [ { "title": "...", "description": "...", "qs": ["exp(2πi/5)"],
+//                     "tolerance": 1e-10, "representation": "burau", "goal": "identity",
+//                     "optimal_length": 10, "solution": "EEEEEEEEEE" } ] }
+//
+// where the tolerance, the representation, the goal, the length of the shortest
+// solution and a shortest solution are optional, and the values of q are
+// expressions as understood by `expression::parse`. The goal is either
+// "identity" or "scalar", the latter asking only for multiples of the identity.
+pub fn parse_level_pack(s: &str) -> Result<Vec<LevelDefinition>, String> {
+    let pack: RawLevelPack = serde_json::from_str(s).map_err(|e| e.to_string())?;
+    if pack.levels.is_empty() {
+        return Err("The level pack contains no levels".to_owned());
+    }
+    pack.levels.into_iter().map(|level| {
+        if let Some(representation) = &level.representation {
+            if representation != "burau" {
+                return Err(format!("Unknown representation '{}'", representation));
+            }
+        }
+        if level.qs.is_empty() {
+            return Err(format!("Level '{}' has no values of q", level.title));
+        }
+        let goal = match level.goal.as_deref() {
+            None | Some("identity") => Goal::Identity,
+            Some("scalar") => Goal::Scalar,
+            Some(goal) => return Err(format!("Unknown goal '{}'", goal))
+        };
+        let (qs, q_displays) = parse_qs(&level.qs)?;
+        let solution = level.solution.as_deref().map(|solution| word::parse_word(solution)
+            .ok_or(format!("Invalid solution '{}' of level '{}'", solution, level.title))).transpose()?;
+        Ok(LevelDefinition {
+            title: level.title,
+            description: level.description,
+            qs,
+            q_displays,
+            tolerance: level.tolerance.unwrap_or(DEFAULT_TOLERANCE),
+            goal,
+            optimal_length: level.optimal_length,
+            solution
+        })
+    }).collect()
+}
+
+// Levels made up by players from a list of values of q separated by commas or
+// semicolons, such as "2.9, 3 + 0.1i".
+pub fn custom_level(qs: &str, description: &str) -> Result<LevelDefinition, String> {
+    let qs: Vec<&str> = qs.split([',', ';']).map(str::trim).filter(|q| !q.is_empty()).collect();
+    if qs.is_empty() {
+        return Err("No values of q given".to_owned());
+    }
+    let (qs, q_displays) = parse_qs(&qs)?;
+    Ok(LevelDefinition {
+        title: format!("Custom: 𝑞 = {}", q_displays.join(", ")),
+        description: description.to_owned(),
+        qs,
+        q_displays,
+        tolerance: DEFAULT_TOLERANCE,
+        goal: Goal::Identity,
+        optimal_length: None,
+        solution: None
+    })
+}
+
+fn parse_qs<S: AsRef<str>>(sources: &[S]) -> Result<(Vec<Complex<f64>>, Vec<String>), String> {
+    let mut qs = vec![];
+    let mut q_displays = vec![];
+    for source in sources {
+        let source = source.as_ref();
+        let expression = expression::parse(source)
+            .map_err(|e| format!("Invalid value of q '{}': {}", source, e))?;
+        let q = expression.evaluate();
+        if q.norm() == 0.0 || !q.norm().is_finite() {
+            return Err(format!("Value of q '{}' must be finite and non-zero", source));
+        }
+        qs.push(q);
+        q_displays.push(expression.to_string());
+    }
+    Ok((qs, q_displays))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn built_in_level_pack_is_valid() {
+        let levels = parse_level_pack(include_str!("levels.json")).unwrap();
+        assert_eq!(levels.len(), 9);
+        assert_eq!(levels[3].q_displays, vec!["1", "−1", "𝑖"]);
+        assert_eq!(levels[6].q_displays, vec!["exp(2π𝑖/3)", "exp(2π𝑖/5)"]);
+        assert!((levels[4].qs[0] - Complex::from_polar(1.0, 2.0 * PI / 3.0)).norm() < 1e-15);
+        assert_eq!(levels[6].optimal_length, None);
+        assert_eq!(levels[7].optimal_length, None);
+    }
+
+    #[test]
+    fn built_in_solutions_are_valid() {
+        for definition in parse_level_pack(include_str!("levels.json")).unwrap() {
+            if let Some(solution) = definition.solution {
+                let mut level = Level::new(definition.qs);
+                level.tolerance = definition.tolerance;
+                level.goal = definition.goal;
+                level.set_word(&solution);
+                assert!(level.is_solved());
+                assert!(definition.optimal_length.is_none_or(|n| n == solution.len()));
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_level_packs_are_rejected() {
+        assert!(parse_level_pack("{}").is_err());
+        assert!(parse_level_pack(r#"{ "levels": [] }"#).is_err());
+        assert!(parse_level_pack(r#"{ "levels": [{ "title": "", "description": "", "qs": [] }] }"#).is_err());
+        assert!(parse_level_pack(r#"{ "levels": [{ "title": "", "description": "", "qs": ["x"] }] }"#).is_err());
+        assert!(parse_level_pack(
+            r#"{ "levels": [{ "title": "", "description": "", "qs": ["1"], "representation": "lkb" }] }"#).is_err());
+        let levels = parse_level_pack(
+            r#"{ "levels": [{ "title": "", "description": "", "qs": ["2"], "tolerance": 0.01 }] }"#).unwrap();
+        assert_eq!(levels[0].tolerance, 0.01);
+        assert_eq!(levels[0].goal, Goal::Identity);
+        let levels = parse_level_pack(
+            r#"{ "levels": [{ "title": "", "description": "", "qs": ["2"], "goal": "scalar" }] }"#).unwrap();
+        assert_eq!(levels[0].goal, Goal::Scalar);
+        assert!(parse_level_pack(
+            r#"{ "levels": [{ "title": "", "description": "", "qs": ["2"], "goal": "centre" }] }"#).is_err());
+        assert!(parse_level_pack(r#"{ "levels": [{ "title": "", "description": "", "qs": ["0"] }] }"#).is_err());
+        assert!(parse_level_pack(
+            r#"{ "levels": [{ "title": "", "description": "", "qs": ["1"], "solution": "NX" }] }"#).is_err());
+    }
+
+    #[test]
+    fn custom_levels_take_lists_of_values() {
+        let level = custom_level("2.9, 3+0.1i; exp(i/2)", "").unwrap();
+        assert_eq!(level.title, "Custom: 𝑞 = 2.9, 3 + 0.1𝑖, exp(𝑖/2)");
+        assert_eq!(level.qs.len(), 3);
+        assert_eq!(level.qs[1], Complex::new(3.0, 0.1));
+        assert!(custom_level(" , ", "").is_err());
+        assert!(custom_level("1, x", "").is_err());
+    }
+}
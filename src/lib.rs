@@ -1,15 +1,33 @@
 mod algebra;
+mod braid;
+mod exact;
+mod export;
+mod expression;
 mod group;
 mod level;
-
-use std::f64::consts::PI;
+mod level_pack;
+mod lyapunov;
+mod relation;
+mod render;
+mod scan;
+mod search;
+mod share;
+mod state;
+mod word;
 
 use js_sys::Array;
 use num::Complex;
 use num::integer::{div_mod_floor};
 use wasm_bindgen::prelude::*;
+use crate::algebra::{Matrix, Metric};
+use crate::exact::LaurentMatrix;
+use crate::export::ExportFormat;
 use crate::group::Direction;
-use crate::level::Level;
+use crate::level::{Goal, Level, Solution, SolutionClass};
+use crate::level_pack::LevelDefinition;
+use crate::render::Notation;
+use crate::scan::Grid;
+use crate::state::{SavedCustomLevel, SavedGame, SavedLevel};
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -21,104 +39,136 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 pub struct Game {
     levels: Vec<Level>,
     qs: Vec<Vec<String>>,
+    level_titles: Vec<String>,
     level_descriptions: Vec<String>,
-    active_level: usize
+    // The source of the values of q of each custom level.
+    custom_qs: Vec<Option<String>>,
+    active_level: usize,
+    // The measure of distance used for the matrices of all levels.
+    metric: Metric
 }
 
+const DEFAULT_LEVEL_PACK: &str = include_str!("levels.json");
+
+// The maximal number of moves searched for hints when the current word is not
+// part of a known solution.
+const HINT_SEARCH_LENGTH: usize = 8;
+
 #[wasm_bindgen]
 impl Game {
     pub fn new() -> Game {
-        let mut levels = vec![];
-        let mut qs = vec![];
-        let mut level_descriptions = vec![];
-
-        // Level 1; q = 1
-        levels.push(Level::new(vec![Complex::new(1.0, 0.0)]));
-        qs.push(vec!["1".to_owned()]);
-        level_descriptions.push(
-            "In this first level, we will find a solution for 𝑞 = 1. That is,
-            each of the four directions correspond to multiplication by one of
-            the four matrices given in the rules in which all instances of 𝑞 have
-            been replaced with 1. This is the simplest case of all of them. Find a
-            solution by using the four buttons below, or by using the keyboard
-            shortcuts. Use the reset button to get back to where you started. Remember
-            that the level is completed when you find a path to the identity matrix."
-            .to_owned());
-        
-        // Level 2; q = -1
-        levels.push(Level::new(vec![Complex::new(-1.0, 0.0)]));
-        qs.push(vec!["−1".to_owned()]);
-        level_descriptions.push(
-            "Hopefully the first level wasn't too bad. In this next one, we use
-            𝑞 = −1 instead. This will make things a bit harder. Pay close attention
-            to how the entries of the matrix change in your quest to find a path
-            to the identity matrix. What's the shortest path you can find?"
-            .to_owned());
-        
-        // Level 3; q = i
-        levels.push(Level::new(vec![Complex::new(0.0, 1.0)]));
-        qs.push(vec!["𝑖".to_owned()]);
-        level_descriptions.push(
-            "In the first two levels, 𝑞 was a real number. In this one, we consider
-            𝑞 = 𝑖, the imaginary unit satisfying 𝑖² = −1. Can you still find a path?"
-            .to_owned());
-        
-        // Level 4: q = 1, -1, i
-        levels.push(Level::new(vec![Complex::new(1.0, 0.0), Complex::new(-1.0, 0.0), Complex::new(0.0, 1.0)]));
-        qs.push(vec!["1".to_owned(), "−1".to_owned(), "𝑖".to_owned()]);
-        level_descriptions.push(
-            "So far, we have tried to find paths for individual values of 𝑞. In this
-            one, we have to find a path that works for all previously considered values
-            at the same time. The level is completed when all three matrices are the
-            identity matrix."
-            .to_owned());
-        
-        // Level 5: q = exp(2*pi*i/3)
-        levels.push(Level::new(vec![Complex::from_polar(1.0, 2.0 * PI / 3.0)]));
-        qs.push(vec!["exp(2π𝑖/3)".to_owned()]);
-        level_descriptions.push(
-            "We continue our journey into the complex plane, this time with a value
-            of 𝑞 that's neither real or imaginary but still lives on the unit circle.
-            At this point, numerical errors will start to show up, and we just require
-            that the result is sufficiently close to the identity matrix."
-            .to_owned());
-        
-        // Level 6: q = exp(2*pi*i/5)
-        levels.push(Level::new(vec![Complex::from_polar(1.0, 2.0 * PI / 5.0)]));
-        qs.push(vec!["exp(2π𝑖/5)".to_owned()]);
-        level_descriptions.push(
-            "Let's do another one on the unit circle."
-            .to_owned());
-        
-        // Level 7: q = exp(2*pi*i/3), exp(2*pi*i/5)
-        levels.push(Level::new(vec![
-            Complex::from_polar(1.0, 2.0 * PI / 3.0),
-            Complex::from_polar(1.0, 2.0 * PI / 5.0)]));
-        qs.push(vec!["exp(2π𝑖/3)".to_owned(), "exp(2π𝑖/5)".to_owned()]);
-        level_descriptions.push(
-            "Can you complete both of the previous levels at once?"
-            .to_owned());
-
-        // Level 8; q = 2
-        levels.push(Level::new(vec![Complex::new(2.0, 0.0)]));
-        qs.push(vec!["2".to_owned()]);
-        level_descriptions.push(
-            "So far, all of our values of 𝑞 have been somewhere on the unit
-            circle. Let's move on to ones that aren't, starting with 𝑞 = 2."
-            .to_owned());
-
-        // Level 9; q = 3
-        levels.push(Level::new(vec![Complex::new(3.0, 0.0)]));
-        qs.push(vec!["3".to_owned()]);
-        level_descriptions.push(
-            "Final level! As our final challenge, we will consider 𝑞 = 3.
-            It is an open question of mathematics whether or not this one is
-            possible. If you find a solution, or if you find that none can 
-            possibly exist, please get in touch."
-            .to_owned());
-
-        let active_level = 0;
-        Game { levels, qs, level_descriptions, active_level }
+        let levels = level_pack::parse_level_pack(DEFAULT_LEVEL_PACK)
+            .expect("the built-in level pack is valid");
+        Self::from_definitions(levels)
+    }
+
+    // Creates a game from a level pack in the format of `levels.json`.
+    pub fn from_level_pack(pack: &str) -> Result<Game, JsValue> {
+        let levels = level_pack::parse_level_pack(pack).map_err(|e| JsValue::from_str(&e))?;
+        Ok(Self::from_definitions(levels))
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    // Appends a level for the given comma separated values of q and returns
+    // its index.
+    pub fn add_custom_level(&mut self, qs: &str, description: &str) -> Result<usize, JsValue> {
+        let definition = level_pack::custom_level(qs, description).map_err(|e| JsValue::from_str(&e))?;
+        self.push_definition(definition, Some(qs.to_owned()));
+        Ok(self.levels.len() - 1)
+    }
+
+    pub fn remove_custom_level(&mut self, i: usize) -> Result<(), JsValue> {
+        if !self.custom_qs.get(i).is_some_and(Option::is_some) {
+            return Err(JsValue::from_str("Only custom levels can be removed"));
+        }
+        self.levels.remove(i);
+        self.qs.remove(i);
+        self.level_titles.remove(i);
+        self.level_descriptions.remove(i);
+        self.custom_qs.remove(i);
+        if self.active_level >= self.levels.len() || self.active_level > i {
+            self.active_level -= 1;
+        }
+        Ok(())
+    }
+
+    pub fn is_custom_level(&self) -> bool {
+        self.custom_qs[self.active_level].is_some()
+    }
+
+    pub fn best_word(&self) -> Option<String> {
+        self.levels[self.active_level].best.as_ref().map(|best| word::word_to_string(&best.word))
+    }
+
+    pub fn best_length(&self) -> Option<usize> {
+        self.levels[self.active_level].best.as_ref().map(|best| best.word.len())
+    }
+
+    // Time at which the best solution was found, in milliseconds since the
+    // Unix epoch.
+    pub fn best_found_at(&self) -> Option<f64> {
+        self.levels[self.active_level].best.as_ref().map(|best| best.found_at)
+    }
+
+    pub fn best_distances(&self) -> Option<Array> {
+        let best = self.levels[self.active_level].best.as_ref()?;
+        let arr = Array::new_with_length(best.distances.len() as u32);
+        for (i, distance) in best.distances.iter().enumerate() {
+            arr.set(i as u32, JsValue::from_f64(*distance));
+        }
+        Some(arr)
+    }
+
+    pub fn completed_levels(&self) -> Array {
+        let arr = Array::new_with_length(self.levels.len() as u32);
+        for (i, level) in self.levels.iter().enumerate() {
+            arr.set(i as u32, JsValue::from_bool(level.completed));
+        }
+        arr
+    }
+
+    // Serialises the words, completion status and best solutions of every
+    // level, together with the custom levels, in a versioned JSON format.
+    pub fn save_state(&self) -> String {
+        let levels = self.levels.iter().zip(&self.level_titles).map(|(level, title)| SavedLevel {
+            title: title.clone(),
+            word: level.word(),
+            completed: level.completed,
+            best: level.best.as_ref().map(|best| word::word_to_string(&best.word)),
+            best_found_at: level.best.as_ref().map(|best| best.found_at),
+            // JSON has no infinities or NaN, so such distances are left out
+            // and computed again when the state is restored.
+            best_distances: level.best.as_ref().map(|best| best.distances.clone())
+                .filter(|distances| distances.iter().all(|distance| distance.is_finite()))
+        }).collect();
+        let custom_levels = self.custom_qs.iter().zip(&self.level_descriptions)
+            .filter_map(|(qs, description)| qs.as_ref().map(|qs| SavedCustomLevel {
+                qs: qs.clone(),
+                description: description.clone()
+            })).collect();
+        state::to_string(&SavedGame {
+            version: state::VERSION,
+            active_level: self.active_level,
+            levels,
+            custom_levels
+        })
+    }
+
+    pub fn load_state(&mut self, saved: &str) -> Result<(), JsValue> {
+        self.restore(saved).map_err(|e| JsValue::from_str(&e))
+    }
+
+    // A short URL safe string identifying the active level and its current
+    // word, which `load_shared` turns back into both.
+    pub fn share(&self) -> String {
+        share::encode(self.active_level, &self.levels[self.active_level].word)
+    }
+
+    pub fn load_shared(&mut self, shared: &str) -> Result<(), JsValue> {
+        self.restore_shared(shared).map_err(|e| JsValue::from_str(&e))
     }
 
     pub fn change_level(&mut self, i: usize) {
@@ -129,6 +179,18 @@ impl Game {
         self.active_level
     }
 
+    pub fn level_title(&self) -> String {
+        self.level_titles[self.active_level].clone()
+    }
+
+    pub fn level_titles(&self) -> Array {
+        let arr = Array::new_with_length(self.level_titles.len() as u32);
+        for (i, title) in self.level_titles.iter().enumerate() {
+            arr.set(i as u32, JsValue::from_str(title));
+        }
+        arr
+    }
+
     pub fn level_description(&self) -> String {
         self.level_descriptions[self.active_level].clone()
     }
@@ -145,58 +207,503 @@ impl Game {
 
     pub fn matrix_entries(&self) -> Array {
         let level = &self.levels[self.active_level];
+        let entries = level.entries_written(Notation::Unicode);
         let length = (level.groups.len() as u32) * 9;
         let arr = Array::new_with_length(length);
         for i in 0..length {
             let (j, k) = div_mod_floor(i, 9);
-            let z = &level.flattened[j as usize][k as usize];
-            // Only show imaginary part if 𝑞 itself is non-real.
-            let s = if level.qs[j as usize].im == 0.0 { f64toa(&z.re) } else { ztoa(z) };
-            arr.set(i as u32, JsValue::from_str(&s));
+            arr.set(i, JsValue::from_str(&entries[j as usize][k as usize]));
+        }
+        arr
+    }
+
+    // The entries of the matrix as Laurent polynomials in q, in row-major
+    // order.
+    pub fn matrix_polynomials(&self) -> Array {
+        let polynomials = self.levels[self.active_level].polynomials(Notation::Unicode);
+        let arr = Array::new_with_length(polynomials.len() as u32);
+        for (i, polynomial) in polynomials.iter().enumerate() {
+            arr.set(i as u32, JsValue::from_str(polynomial));
         }
         arr
     }
 
+    // The current matrices as LaTeX pmatrix environments, one line per value
+    // of q.
+    pub fn matrices_latex(&self) -> String {
+        self.matrices_written(Notation::Latex)
+    }
+
+    // The current matrices as MathML, one math element per value of q.
+    pub fn matrices_mathml(&self) -> String {
+        self.matrices_written(Notation::MathMl)
+    }
+
+    pub fn generator_matrix_latex(&self, direction: Direction) -> String {
+        Self::generator_matrix_written(direction, Notation::Latex)
+    }
+
+    pub fn generator_matrix_mathml(&self, direction: Direction) -> String {
+        Self::generator_matrix_written(direction, Notation::MathMl)
+    }
+
+    // The matrix of a single move, first for symbolic q and then evaluated at
+    // each of the level's values of q, each as nine entries in row-major order.
+    pub fn generator_matrices(&self, direction: Direction) -> Array {
+        let level = &self.levels[self.active_level];
+        let mut matrices = vec![LaurentMatrix::generator(&direction).write(Notation::Unicode)];
+        matrices.extend(level.generator_entries_written(&direction, Notation::Unicode));
+        let arr = Array::new_with_length(matrices.len() as u32);
+        for (i, entries) in matrices.iter().enumerate() {
+            let entries_arr = Array::new_with_length(9);
+            for (j, entry) in entries.iter().enumerate() {
+                entries_arr.set(j as u32, JsValue::from_str(entry));
+            }
+            arr.set(i as u32, entries_arr.into());
+        }
+        arr
+    }
+
+    // The entries of the matrix of a single move at each value of q, in the
+    // layout of `matrix_values`.
+    pub fn generator_matrix_values(&self, direction: Direction) -> Vec<f64> {
+        self.levels[self.active_level].generator_entries(&direction)
+    }
+
     pub fn matrix_is_identity(&self) -> Array {
         let level = &self.levels[self.active_level];
         let length = level.groups.len();
         let arr = Array::new_with_length(length as u32);
         for (i, group) in level.groups.iter().enumerate() {
-            arr.set(i as u32, JsValue::from_bool(group.current_is_identity()));
+            arr.set(i as u32, JsValue::from_bool(group.current_is_identity(level.tolerance)));
         }
         arr
     }
 
-    pub fn distance(&self) -> Array {
+    pub fn matrix_is_scalar(&self) -> Array {
         let level = &self.levels[self.active_level];
         let length = level.groups.len();
         let arr = Array::new_with_length(length as u32);
         for (i, group) in level.groups.iter().enumerate() {
+            arr.set(i as u32, JsValue::from_bool(group.current().is_scalar(level.tolerance)));
+        }
+        arr
+    }
+
+    pub fn distance(&self) -> Array {
+        let level = &self.levels[self.active_level];
+        let distances = level.distances();
+        let arr = Array::new_with_length(distances.len() as u32);
+        for (i, distance) in distances.iter().enumerate() {
             let distance_string = if level.word.is_empty() {
                 "∞".to_owned()
             } else {
-                format!("{:.5}", group.distance_from_identity())
+                format!("{:.5}", distance)
             };
             arr.set(i as u32, JsValue::from_str(&distance_string));
         }
         arr
     }
 
+    // The real and imaginary parts of the entries of the matrices, nine
+    // entries per value of q in row-major order, as a Float64Array.
+    pub fn matrix_values(&self) -> Vec<f64> {
+        self.levels[self.active_level].entries()
+    }
+
+    // Every move has determinant 1, so the distance of the determinants from 1
+    // measures the rounding errors accumulated along the word. Like the other
+    // invariants below, they are given as real and imaginary parts for each
+    // value of q.
+    pub fn determinants(&self) -> Vec<f64> {
+        self.levels[self.active_level].current_values(|m| [m.determinant()])
+    }
+
+    pub fn traces(&self) -> Vec<f64> {
+        self.levels[self.active_level].current_values(|m| [m.trace()])
+    }
+
+    // The trace of the current matrix for symbolic q.
+    pub fn trace_polynomial(&self) -> String {
+        self.levels[self.active_level].symbolic().trace().to_string()
+    }
+
+    // The coefficients of each characteristic polynomial, lowest degree first.
+    pub fn characteristic_polynomials(&self) -> Vec<f64> {
+        self.levels[self.active_level].current_values(Matrix::characteristic_polynomial)
+    }
+
+    pub fn eigenvalues(&self) -> Vec<f64> {
+        self.levels[self.active_level].current_values(Matrix::eigenvalues)
+    }
+
+    // The entries of the inverse of each matrix, in the layout of
+    // `matrix_values`.
+    pub fn inverse_values(&self) -> Vec<f64> {
+        self.levels[self.active_level].current_values(|m| m.inverse().map_or([Complex::new(f64::NAN, f64::NAN); 9], |i| i.flatten()))
+    }
+
+    // Four values per value of q for every prefix of the word, starting with
+    // the empty one: the distance from the identity, the largest modulus of an
+    // entry, and the real and imaginary parts of the determinant, as a
+    // Float64Array.
+    pub fn history(&self) -> Vec<f64> {
+        self.levels[self.active_level].history_values()
+    }
+
+    // Estimates the growth rate of log ‖M‖ along random reduced walks at each
+    // of the given comma separated values of q, as CSV with a row per value.
+    pub fn lyapunov_exponents(qs: &str, walks: usize, length: usize, seed: u32) -> Result<String, JsValue> {
+        Self::estimate_lyapunov_exponents(qs, walks, length, seed).map_err(|e| JsValue::from_str(&e))
+    }
+
+    // The distances from the identity of the product of a word at each point
+    // of a grid, in the chosen metric and in the order of `Grid::points`, as a
+    // Float64Array.
+    pub fn scan_grid(&self, word: &str, grid: &Grid) -> Result<Vec<f64>, JsValue> {
+        self.scan(word, &grid.points()).map_err(|e| JsValue::from_str(&e))
+    }
+
+    // The same distances as `scan_grid` as CSV with a row per point.
+    pub fn scan_grid_csv(&self, word: &str, grid: &Grid) -> Result<String, JsValue> {
+        let qs = grid.points();
+        let distances = self.scan(word, &qs).map_err(|e| JsValue::from_str(&e))?;
+        Ok(scan::to_csv(&qs, &distances))
+    }
+
+    // The same distances as `scan_grid` as a PPM image with a pixel per point.
+    pub fn scan_grid_ppm(&self, word: &str, grid: &Grid) -> Result<Vec<u8>, JsValue> {
+        let distances = self.scan(word, &grid.points()).map_err(|e| JsValue::from_str(&e))?;
+        Ok(scan::to_ppm(&distances, grid.width, grid.height))
+    }
+
+    // The distances along the segment between two values of q, as CSV.
+    pub fn scan_segment_csv(&self, word: &str, from: &str, to: &str, count: usize) -> Result<String, JsValue> {
+        self.scan_segment(word, from, to, count).map_err(|e| JsValue::from_str(&e))
+    }
+
+    // The greatest common divisor of the numerators of the entries of the
+    // product of a word minus the identity, whose roots are the values of q at
+    // which the word is a relation. It is zero for words that are relations for
+    // every q.
+    pub fn relation_polynomial(word: &str) -> Result<String, JsValue> {
+        let locus = Self::relation_locus(word).map_err(|e| JsValue::from_str(&e))?;
+        Ok(locus.polynomial.to_string())
+    }
+
+    // The values of q at which a word is a relation, roots of unity first and
+    // written exactly, followed by the others written numerically.
+    pub fn relation_roots(word: &str) -> Result<Array, JsValue> {
+        let locus = Self::relation_locus(word).map_err(|e| JsValue::from_str(&e))?;
+        let roots = locus.roots_written(Notation::Unicode);
+        let arr = Array::new_with_length(roots.len() as u32);
+        for (i, root) in roots.iter().enumerate() {
+            arr.set(i as u32, JsValue::from_str(root));
+        }
+        Ok(arr)
+    }
+
+    // The real and imaginary parts of the roots in the order of
+    // `relation_roots`, as a Float64Array.
+    pub fn relation_root_values(word: &str) -> Result<Vec<f64>, JsValue> {
+        let locus = Self::relation_locus(word).map_err(|e| JsValue::from_str(&e))?;
+        Ok(locus.root_values().iter().flat_map(|z| [z.re, z.im]).collect())
+    }
+
+    // The distances of the matrices from the goal of the level in the chosen
+    // metric as a Float64Array.
+    pub fn distances(&self) -> Vec<f64> {
+        self.levels[self.active_level].distances()
+    }
+
     pub fn push(&mut self, direction: Direction) {
-        self.levels[self.active_level].push(direction);
+        let level = &mut self.levels[self.active_level];
+        level.replay = None;
+        level.push(direction);
     }
 
     pub fn reset(&mut self) {
-        self.levels[self.active_level].reset();
+        let level = &mut self.levels[self.active_level];
+        level.replay = None;
+        level.reset();
+    }
+
+    // Resets the level and prepares the given word for being played move by
+    // move, so that the matrices can be followed along it.
+    pub fn load_word(&mut self, word: &str) -> Result<(), JsValue> {
+        let word = word::parse_word(word).ok_or_else(|| JsValue::from_str(&format!("Invalid word '{}'", word)))?;
+        self.levels[self.active_level].start_replay(word);
+        Ok(())
+    }
+
+    // Makes the next move of the loaded word, returning false at its end.
+    pub fn step_forward(&mut self) -> bool {
+        self.levels[self.active_level].step_forward()
+    }
+
+    // Undoes the last move of the loaded word, returning false at its start.
+    pub fn step_back(&mut self) -> bool {
+        self.levels[self.active_level].step_back()
+    }
+
+    // The number of moves of the loaded word that have been made.
+    pub fn replay_position(&self) -> Option<usize> {
+        self.levels[self.active_level].replay.as_ref().map(|replay| replay.position)
+    }
+
+    pub fn replay_length(&self) -> Option<usize> {
+        self.levels[self.active_level].replay.as_ref().map(|replay| replay.word.len())
     }
 
     pub fn is_solved(&self) -> bool {
         self.levels[self.active_level].is_solved()
     }
 
+    // Whether the current word solves the level and was played rather than
+    // loaded or replayed, so that the level counts as completed by it.
+    pub fn is_credited(&self) -> bool {
+        self.levels[self.active_level].credited
+    }
+
+    // Whether every matrix is a multiple of the identity, which is the goal
+    // of some levels and a near miss in others.
+    pub fn is_solved_up_to_scalar(&self) -> bool {
+        self.levels[self.active_level].is_solved_up_to_scalar()
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    pub fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+        for level in self.levels.iter_mut() {
+            level.metric = metric;
+        }
+    }
+
+    pub fn goal(&self) -> Goal {
+        self.levels[self.active_level].goal
+    }
+
+    // The next move of a shortest solution extending the current word, if one
+    // is known or found within the search budget.
+    pub fn hint(&self) -> Option<Direction> {
+        self.levels[self.active_level].hint(HINT_SEARCH_LENGTH)
+    }
+
+    pub fn optimal_length(&self) -> Option<usize> {
+        self.levels[self.active_level].optimal_length
+    }
+
+    // Searches all words of length at most `max_length` for a shortest
+    // solution and, if one is found, rates later solutions against it. This is
+    // meant for levels such as custom ones whose optimum is not known.
+    pub fn find_optimal_length(&mut self, max_length: usize) -> Option<usize> {
+        let level = &mut self.levels[self.active_level];
+        if level.optimal_length.is_none() {
+            level.optimal_length = search::shortest_relation(&level.qs, max_length, level.tolerance, level.goal).map(|w| w.len());
+        }
+        level.optimal_length
+    }
+
+    // Between one and three stars for the current solution, where the optimal
+    // solution length of the level is known.
+    pub fn rating(&self) -> Option<u8> {
+        self.levels[self.active_level].rating()
+    }
+
+    pub fn solution_class(&self) -> Option<SolutionClass> {
+        self.levels[self.active_level].solution_class()
+    }
+
+    pub fn classify_word(&self, word: &str) -> Option<SolutionClass> {
+        word::parse_word(word).map(|w| level::classify(&w))
+    }
+
+    pub fn is_burau_kernel_element(&self) -> bool {
+        self.levels[self.active_level].is_burau_kernel_element()
+    }
+
     pub fn word(&self) -> String {
         self.levels[self.active_level].word()
     }
+
+    pub fn canonical_word(&self) -> String {
+        self.levels[self.active_level].canonical_word()
+    }
+
+    // Takes words separated by whitespace or commas and returns the canonical
+    // forms of the distinct conjugacy classes among them.
+    pub fn distinct_up_to_conjugacy(&self, words: &str) -> Option<Array> {
+        let words = words.split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|w| !w.is_empty())
+            .map(word::parse_word)
+            .collect::<Option<Vec<_>>>()?;
+        let distinct = word::distinct_up_to_conjugacy(&words);
+        let arr = Array::new_with_length(distinct.len() as u32);
+        for (i, w) in distinct.iter().enumerate() {
+            arr.set(i as u32, JsValue::from_str(&word::word_to_string(w)));
+        }
+        Some(arr)
+    }
+
+    // Script checking the current word in a computer algebra system, either
+    // for symbolic q or for each of the level's values of q.
+    pub fn export(&self, format: ExportFormat, symbolic: bool) -> Result<String, JsValue> {
+        let level = &self.levels[self.active_level];
+        let qs = if symbolic { None } else { Some(&level.qs[..]) };
+        export::export(format, &level.word, qs).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn braid_word(&self) -> String {
+        braid::braid_to_string(&self.levels[self.active_level].braid())
+    }
+
+    // Whether a braid, such as "σ₁σ₃⁻¹σ₂", is trivial, or `None` if it cannot
+    // be parsed.
+    pub fn is_trivial_braid(&self, braid_word: &str) -> Option<bool> {
+        braid::parse_braid(braid_word).map(|braid| braid::is_trivial(&braid))
+    }
+
+    pub fn word_from_braid(&self, braid_word: &str) -> Option<String> {
+        let braid = braid::parse_braid(braid_word)?;
+        let word = braid::braid_to_word(&braid)?;
+        Some(word::word_to_string(&word))
+    }
+}
+
+impl Game {
+    fn from_definitions(definitions: Vec<LevelDefinition>) -> Game {
+        let mut game = Game {
+            levels: vec![],
+            qs: vec![],
+            level_titles: vec![],
+            level_descriptions: vec![],
+            custom_qs: vec![],
+            active_level: 0,
+            metric: Metric::Frobenius
+        };
+        for definition in definitions {
+            game.push_definition(definition, None);
+        }
+        game
+    }
+
+    fn push_definition(&mut self, definition: LevelDefinition, custom_qs: Option<String>) {
+        let mut level = Level::new(definition.qs);
+        level.tolerance = definition.tolerance;
+        level.goal = definition.goal;
+        level.metric = self.metric;
+        level.optimal_length = definition.optimal_length;
+        level.solution = definition.solution;
+        self.levels.push(level);
+        self.qs.push(definition.q_displays);
+        self.level_titles.push(definition.title);
+        self.level_descriptions.push(definition.description);
+        self.custom_qs.push(custom_qs);
+    }
+
+    fn estimate_lyapunov_exponents(qs: &str, walks: usize, length: usize, seed: u32) -> Result<String, String> {
+        if walks == 0 || length == 0 {
+            return Err("At least one walk of at least one move is needed".to_owned());
+        }
+        let definition = level_pack::custom_level(qs, "")?;
+        Ok(lyapunov::estimates_csv(&definition.qs, walks, length, seed as u64))
+    }
+
+    fn scan(&self, word: &str, qs: &[Complex<f64>]) -> Result<Vec<f64>, String> {
+        let word = word::parse_word(word).ok_or(format!("Invalid word '{}'", word))?;
+        Ok(scan::distances(&word, qs, self.metric))
+    }
+
+    fn scan_segment(&self, word: &str, from: &str, to: &str, count: usize) -> Result<String, String> {
+        let ends = level_pack::custom_level(&format!("{}, {}", from, to), "")?.qs;
+        if ends.len() != 2 {
+            return Err("Both ends of the segment must be single values of q".to_owned());
+        }
+        let qs = scan::segment(ends[0], ends[1], count);
+        Ok(scan::to_csv(&qs, &self.scan(word, &qs)?))
+    }
+
+    fn relation_locus(word: &str) -> Result<relation::Locus, String> {
+        let word = word::parse_word(word).ok_or(format!("Invalid word '{}'", word))?;
+        Ok(relation::locus(&word))
+    }
+
+    fn restore_shared(&mut self, shared: &str) -> Result<(), String> {
+        let (i, word) = share::decode(shared)?;
+        if i >= self.levels.len() {
+            return Err(format!("There is no level {}", i + 1));
+        }
+        self.active_level = i;
+        self.levels[i].set_word(&word);
+        Ok(())
+    }
+
+    fn matrices_written(&self, notation: Notation) -> String {
+        let level = &self.levels[self.active_level];
+        render::labelled_matrices(&level.qs, &level.entries_written(notation), notation)
+    }
+
+    fn generator_matrix_written(direction: Direction, notation: Notation) -> String {
+        let label = match notation {
+            Notation::MathMl => format!("<mi>{}</mi>", direction.letter()),
+            _ => direction.letter().to_string()
+        };
+        render::equation(&label, &LaurentMatrix::generator(&direction).write(notation), notation)
+    }
+
+    // Everything is validated before the game is touched, so that a failed
+    // restore leaves the current progress intact. Otherwise the progress is
+    // replaced by the saved one, matching levels by title and repeated titles
+    // in order, and levels missing from either side are left out.
+    fn restore(&mut self, saved: &str) -> Result<(), String> {
+        let saved = state::from_str(saved)?;
+        let custom_definitions = saved.custom_levels.iter()
+            .map(|custom| level_pack::custom_level(&custom.qs, &custom.description).map(|d| (d, custom.qs.clone())))
+            .collect::<Result<Vec<_>, _>>()?;
+        let parse = |w: &str| word::parse_word(w).ok_or(format!("Invalid word '{}'", w));
+        let words = saved.levels.iter()
+            .map(|level| Ok((parse(&level.word)?, level.best.as_deref().map(parse).transpose()?)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        while let Some(i) = self.custom_qs.iter().rposition(Option::is_some) {
+            self.remove_custom_level(i).ok();
+        }
+        for (definition, qs) in custom_definitions {
+            self.push_definition(definition, Some(qs));
+        }
+        for level in self.levels.iter_mut() {
+            level.reset();
+            level.replay = None;
+            level.completed = false;
+            level.best = None;
+        }
+        let mut indices = vec![];
+        for (i, saved_level) in saved.levels.iter().enumerate() {
+            let occurrence = saved.levels[..i].iter().filter(|other| other.title == saved_level.title).count();
+            indices.push(self.level_titles.iter().enumerate()
+                .filter(|(_, title)| **title == saved_level.title)
+                .map(|(j, _)| j)
+                .nth(occurrence));
+        }
+        for ((saved_level, (word, best)), index) in saved.levels.into_iter().zip(words).zip(&indices) {
+            let level = match index {
+                Some(j) => &mut self.levels[*j],
+                None => continue
+            };
+            if let Some(best) = best {
+                let distances = saved_level.best_distances.unwrap_or_else(|| level.distances_for(&best));
+                let found_at = saved_level.best_found_at.unwrap_or(0.0);
+                level.offer_best(Solution { word: best, found_at, distances });
+            }
+            level.set_word(&word);
+            level.completed = saved_level.completed;
+        }
+        self.active_level = indices.get(saved.active_level).copied().flatten().unwrap_or(0);
+        Ok(())
+    }
 }
 
 impl Default for Game {   
@@ -205,28 +712,244 @@ impl Default for Game {
     }                     
 }
 
-fn f64toa(x: &f64) -> String {
-    let mut buf = Vec::new();
-    dtoa::write(&mut buf, *x).unwrap();
-    std::str::from_utf8(&buf).unwrap().to_string()
-}
-
-fn ztoa(z: &Complex<f64>) -> String {
-    format!("{} + {}𝑖", f64toa(&z.re), f64toa(&z.im))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn f64toa_works_small_number() {
-        let actual = f64toa(&2.0);
-        assert_eq!("2.0", actual);
+    fn custom_levels_can_be_added_and_removed() {
+        let mut game = Game::new();
+        let i = game.add_custom_level("2.9, 3 + 0.1i", "Near three").unwrap();
+        assert_eq!(i, 9);
+        assert_eq!(game.level_count(), 10);
+        game.change_level(i);
+        assert!(game.is_custom_level());
+        assert_eq!(game.level_description(), "Near three");
+        game.remove_custom_level(i).unwrap();
+        assert_eq!(game.level_count(), 9);
+        assert_eq!(game.active_level(), 8);
+        assert!(!game.is_custom_level());
+    }
+
+    #[test]
+    fn solutions_are_rated() {
+        let mut game = Game::new();
+        assert_eq!(game.optimal_length(), Some(2));
+        assert_eq!(game.rating(), None);
+        game.push(Direction::North);
+        game.push(Direction::North);
+        assert_eq!(game.rating(), Some(3));
+
+        game.add_custom_level("-1", "").unwrap();
+        game.change_level(9);
+        assert_eq!(game.optimal_length(), None);
+        assert_eq!(game.find_optimal_length(3), None);
+        assert_eq!(game.find_optimal_length(6), Some(4));
     }
+
     #[test]
-    fn f64toa_works_large_number() {
-        let actual = f64toa(&2e25);
-        assert_eq!("2e25", actual);
+    fn hints_solve_early_levels() {
+        let mut game = Game::new();
+        for i in 0..4 {
+            game.change_level(i);
+            game.push(Direction::West);
+            while let Some(direction) = game.hint() {
+                game.push(direction);
+            }
+            assert!(game.is_solved());
+        }
+        game.change_level(8);
+        assert_eq!(game.hint(), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn shared_words_can_be_loaded() {
+        let mut game = Game::new();
+        game.change_level(1);
+        for direction in [Direction::South, Direction::West, Direction::North, Direction::East] {
+            game.push(direction);
+        }
+        let shared = game.share();
+
+        // Loading a solution doesn't count as solving the level.
+        let mut other = Game::new();
+        other.restore_shared(&shared).unwrap();
+        assert_eq!(other.active_level(), 1);
+        assert_eq!(other.word(), "SWNE");
+        assert!(other.is_solved());
+        assert!(!other.levels[1].completed);
+        assert!(!other.is_credited());
+        assert!(other.restore_shared(&share::encode(9, &[])).is_err());
+        assert!(other.restore_shared("AAAA").is_err());
+        assert_eq!(other.active_level(), 1);
+    }
+
+    #[test]
+    fn words_can_be_replayed() {
+        let mut game = Game::new();
+        game.change_level(1);
+        game.push(Direction::North);
+        game.load_word("SWNE").unwrap();
+        assert_eq!(game.word(), "");
+        assert_eq!(game.replay_length(), Some(4));
+        while game.step_forward() {}
+        assert!(game.is_solved());
+        assert_eq!(game.replay_position(), Some(4));
+        game.step_back();
+        assert_eq!(game.word(), "SWN");
+        game.push(Direction::East);
+        assert_eq!(game.replay_position(), None);
+        assert!(!game.step_back());
+    }
+
+    #[test]
+    fn matrices_are_rendered() {
+        let mut game = Game::new();
+        game.change_level(3);
+        game.push(Direction::East);
+        let latex = game.matrices_latex();
+        assert_eq!(latex.lines().count(), 3);
+        assert!(latex.starts_with("q = 1 \\colon \\begin{pmatrix} -1 & 1 & 0 \\\\ 0 & 1 & 0 \\\\ 0 & 1 & -1 \\end{pmatrix}"));
+        assert_eq!(game.matrices_mathml().matches("<math display=\"block\">").count(), 3);
+        assert_eq!(game.generator_matrix_latex(Direction::West),
+            "W = \\begin{pmatrix} -q & q & 0 \\\\ 0 & 1 & 0 \\\\ 0 & q^{-1} & -q^{-1} \\end{pmatrix}");
+    }
+
+    #[test]
+    fn invariants_are_given_per_q() {
+        let mut game = Game::new();
+        game.change_level(3);
+        game.push(Direction::North);
+        assert_eq!(game.traces().len(), 6);
+        assert_eq!(game.trace_polynomial(), "−𝑞⁻¹ + 1 − 𝑞");
+        assert_eq!(game.characteristic_polynomials().len(), 24);
+        assert_eq!(game.eigenvalues().len(), 18);
+        let inverse = game.inverse_values();
+        game.push(Direction::South);
+        game.push(Direction::South);
+        let south = game.matrix_values();
+        assert!(inverse.iter().zip(&south).all(|(a, b)| (a - b).abs() < 1e-12));
+    }
+
+    #[test]
+    fn metric_applies_to_all_levels() {
+        let mut game = Game::new();
+        game.set_metric(Metric::LogSpectralRadius);
+        let i = game.add_custom_level("3", "").unwrap();
+        assert!(game.levels.iter().all(|level| level.metric == Metric::LogSpectralRadius));
+        game.change_level(i);
+        for _ in 0..10 {
+            game.push(Direction::East);
+        }
+        assert!(game.distances()[0] > 1.0);
+        assert_eq!(game.metric(), Metric::LogSpectralRadius);
+    }
+
+    #[test]
+    fn lyapunov_exponents_are_estimated() {
+        let csv = Game::estimate_lyapunov_exponents("2.9, 3", 3, 20, 1).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.lines().nth(1).unwrap().starts_with("2.9,0.0,"));
+        assert!(Game::estimate_lyapunov_exponents("3", 0, 20, 1).is_err());
+        assert!(Game::estimate_lyapunov_exponents("x", 3, 20, 1).is_err());
+    }
+
+    #[test]
+    fn words_can_be_scanned_over_q() {
+        let game = Game::new();
+        let grid = Grid::new(-1.0, 1.0, -1.0, 1.0, 3, 3);
+        let distances = game.scan("SWNE", &grid.points()).unwrap();
+        assert_eq!(distances.len(), 9);
+        // The relation of level 2 holds at q = −1, on the left of the middle
+        // row, but not at q = i in the middle of the top row.
+        assert!(distances[3] < 1e-10);
+        assert!(distances[1] > 1e-3);
+        assert!(game.scan("SWNX", &grid.points()).is_err());
+        let csv = game.scan_segment("EEEE", "2.9", "3", 11).unwrap();
+        assert_eq!(csv.lines().count(), 13);
+        assert!(game.scan_segment("EEEE", "2.9, 3", "3", 11).is_err());
+    }
+
+    #[test]
+    fn relations_are_located() {
+        let locus = Game::relation_locus("EEEEEE").unwrap();
+        assert_eq!(locus.roots_written(Notation::Unicode), vec!["1", "ζ₃", "ζ₃²", "ζ₆", "ζ₆⁵"]);
+        assert_eq!(locus.root_values().len(), 5);
+        assert!(Game::relation_locus("EEX").is_err());
+    }
+
+    #[test]
+    fn non_finite_distances_are_not_saved() {
+        let mut game = Game::new();
+        game.push(Direction::North);
+        game.push(Direction::North);
+        game.levels[0].best.as_mut().unwrap().distances = vec![f64::NAN];
+        let saved = game.save_state();
+        assert_eq!(state::from_str(&saved).unwrap().levels[0].best_distances, None);
+        let mut restored = Game::new();
+        restored.restore(&saved).unwrap();
+        assert_eq!(restored.levels[0].best.as_ref().unwrap().distances, vec![0.0]);
+    }
+
+    #[test]
+    fn braids_can_be_checked_for_triviality() {
+        let game = Game::new();
+        assert_eq!(game.is_trivial_braid("σ₁σ₂σ₁σ₂⁻¹σ₁⁻¹σ₂⁻¹"), Some(true));
+        assert_eq!(game.is_trivial_braid("σ₁σ₃⁻¹"), Some(false));
+        assert_eq!(game.is_trivial_braid("x"), None);
+    }
+
+    #[test]
+    fn restoring_replaces_progress_by_title() {
+        let mut game = Game::new();
+        game.change_level(1);
+        for direction in [Direction::South, Direction::West, Direction::North, Direction::East] {
+            game.push(direction);
+        }
+        let saved = game.save_state();
+
+        // A pack with the first two levels swapped and one level added.
+        let mut pack: serde_json::Value = serde_json::from_str(DEFAULT_LEVEL_PACK).unwrap();
+        let levels = pack["levels"].as_array_mut().unwrap();
+        levels.swap(0, 1);
+        levels.push(serde_json::json!({ "title": "New", "description": "", "qs": ["2"] }));
+        let mut restored = Game::from_definitions(level_pack::parse_level_pack(&pack.to_string()).unwrap());
+        restored.change_level(1);
+        restored.push(Direction::North);
+        restored.push(Direction::North);
+        assert!(restored.levels[1].completed);
+
+        restored.restore(&saved).unwrap();
+        assert_eq!(restored.active_level(), 0);
+        assert_eq!(restored.word(), "SWNE");
+        assert!(restored.levels[0].completed);
+        assert!(!restored.levels[1].completed);
+        assert_eq!(restored.levels[1].best, None);
+        assert_eq!(restored.levels[1].word(), "");
+    }
+
+    #[test]
+    fn state_can_be_saved_and_restored() {
+        let mut game = Game::new();
+        game.push(Direction::North);
+        game.push(Direction::North);
+        game.levels[0].best.as_mut().unwrap().found_at = 1.0;
+        game.add_custom_level("2.9", "").unwrap();
+        game.change_level(9);
+        game.push(Direction::East);
+        let saved = game.save_state();
+
+        let mut restored = Game::new();
+        restored.restore(&saved).unwrap();
+        assert_eq!(restored.level_count(), 10);
+        assert_eq!(restored.active_level(), 9);
+        assert_eq!(restored.word(), "E");
+        assert!(restored.levels[0].completed);
+        assert_eq!(restored.levels[0].best, game.levels[0].best);
+        assert_eq!(restored.save_state(), saved);
+
+        assert!(restored.restore("{}").is_err());
+        assert_eq!(restored.level_count(), 10);
+    }
+
+}
@@ -0,0 +1,123 @@
+use num::Complex;
+
+use crate::group::{Direction, Group};
+use crate::render::f64toa;
+
+const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
+
+// A xorshift64* generator, which is plenty for choosing moves and keeps runs
+// reproducible from a seed.
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // The state must never be zero.
+        Rng { state: seed ^ 0x9e37_79b9_7f4a_7c15 | 1 }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        // The top bits are the best ones of xorshift64*.
+        ((self.next_u64() >> 32) * n) >> 32
+    }
+}
+
+// An estimate of the top Lyapunov exponent together with its standard error
+// over the walks it is averaged over.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Estimate {
+    pub exponent: f64,
+    pub standard_error: f64
+}
+
+// The growth rate of log ‖M‖ along a random reduced walk of the given length,
+// renormalising after each move to stay within floating point range.
+fn walk(q: &Complex<f64>, length: usize, rng: &mut Rng) -> f64 {
+    let mut group = Group::new(q);
+    let mut log_norm = 0.0;
+    let mut last: Option<Direction> = None;
+    for _ in 0..length {
+        let direction = loop {
+            let direction = DIRECTIONS[rng.below(4) as usize];
+            if last.is_none_or(|last| direction != last.inverse()) {
+                break direction;
+            }
+        };
+        group.push(&direction);
+        log_norm += group.renormalise();
+        last = Some(direction);
+    }
+    log_norm / length as f64
+}
+
+pub fn estimate(q: &Complex<f64>, walks: usize, length: usize, rng: &mut Rng) -> Estimate {
+    let rates: Vec<f64> = (0..walks).map(|_| walk(q, length, rng)).collect();
+    let n = rates.len() as f64;
+    let exponent = rates.iter().sum::<f64>() / n;
+    let variance = if rates.len() > 1 {
+        rates.iter().map(|rate| (rate - exponent).powi(2)).sum::<f64>() / (n - 1.0)
+    } else {
+        0.0
+    };
+    Estimate { exponent, standard_error: (variance / n).sqrt() }
+}
+
+// Estimates the exponent at each value of q with the same number of walks of
+// the same length, one CSV row per value.
+pub fn estimates_csv(qs: &[Complex<f64>], walks: usize, length: usize, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut csv = "q_re,q_im,exponent,standard_error\n".to_owned();
+    for q in qs {
+        let estimate = estimate(q, walks, length, &mut rng);
+        let row = [q.re, q.im, estimate.exponent, estimate.standard_error].iter().map(f64toa).collect::<Vec<_>>();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng_is_reproducible_and_in_range() {
+        let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+        let draws: Vec<u64> = (0..1000).map(|_| a.below(4)).collect();
+        assert!(draws.iter().all(|draw| *draw < 4));
+        assert!((0..4).all(|i| draws.contains(&i)));
+        assert_eq!(draws, (0..1000).map(|_| b.below(4)).collect::<Vec<_>>());
+        assert_ne!(Rng::new(0).next_u64(), 0);
+    }
+
+    #[test]
+    fn walks_grow_for_large_q() {
+        let mut rng = Rng::new(1);
+        // At q = 1 the matrices are permutation matrices up to sign.
+        let one = estimate(&Complex::new(1.0, 0.0), 10, 200, &mut rng);
+        assert!(one.exponent.abs() < 0.02);
+        let three = estimate(&Complex::new(3.0, 0.0), 10, 200, &mut rng);
+        assert!(three.exponent > 0.1);
+        assert!(three.standard_error > 0.0 && three.standard_error < three.exponent);
+    }
+
+    #[test]
+    fn estimates_are_written_as_csv() {
+        let qs = [Complex::new(2.0, 0.0), Complex::new(3.0, 0.5)];
+        let csv = estimates_csv(&qs, 4, 50, 3);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "q_re,q_im,exponent,standard_error");
+        assert!(lines[2].starts_with("3.0,0.5,"));
+        assert_eq!(lines[1].split(',').count(), 4);
+        assert_eq!(csv, estimates_csv(&qs, 4, 50, 3));
+    }
+}
@@ -0,0 +1,298 @@
+use std::f64::consts::PI;
+
+use num::{BigInt, Complex, Integer, Signed, ToPrimitive, Zero};
+
+use crate::exact::{cyclotomic, recognise, ExactValue, Laurent, LaurentMatrix};
+use crate::group::Direction;
+use crate::render::{self, Notation};
+
+// Polynomials with integer coefficients, lowest degree first and without
+// vanishing leading coefficients, so that the zero polynomial is empty.
+type Polynomial = Vec<BigInt>;
+
+// The values of q at which a word is a relation.
+pub struct Locus {
+    // The greatest common divisor of the numerators of the entries of the
+    // product minus the identity, which is zero exactly when the word is a
+    // relation for every q.
+    pub polynomial: Laurent,
+    // Roots of the polynomial that are roots of unity, found exactly.
+    pub roots_of_unity: Vec<ExactValue>,
+    // The remaining roots, found numerically.
+    pub other_roots: Vec<Complex<f64>>
+}
+
+impl Locus {
+    pub fn roots_written(&self, notation: Notation) -> Vec<String> {
+        self.roots_of_unity.iter().map(|q| render::exact_value(*q, notation))
+            .chain(self.other_roots.iter().map(|q| render::complex(q, q.im != 0.0, notation)))
+            .collect()
+    }
+
+    pub fn root_values(&self) -> Vec<Complex<f64>> {
+        self.roots_of_unity.iter().map(|q| match q {
+            ExactValue::RootOfUnity { order, power } => Complex::from_polar(1.0, 2.0 * PI * *power as f64 / *order as f64),
+            ExactValue::Integer(n) => Complex::new(*n as f64, 0.0)
+        }).chain(self.other_roots.iter().copied()).collect()
+    }
+}
+
+pub fn locus(word: &[Direction]) -> Locus {
+    let product = word.iter().fold(LaurentMatrix::identity(), |acc, direction| &acc * &LaurentMatrix::generator(direction));
+    let minus_one = Laurent::from_summands(&[(0, -1)]);
+    let g = product.d.iter().enumerate()
+        .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, entry)| (i, j, entry)))
+        .map(|(i, j, entry)| if i == j { numerator(&(entry + &minus_one)) } else { numerator(entry) })
+        .fold(vec![], |acc, p| gcd(&acc, &p));
+    let (roots_of_unity, other_roots) = roots(&g);
+    Locus { polynomial: Laurent::from_coefficients(&g), roots_of_unity, other_roots }
+}
+
+// The distinct roots of a polynomial, split into roots of unity and others.
+fn roots(g: &[BigInt]) -> (Vec<ExactValue>, Vec<Complex<f64>>) {
+    if g.is_empty() {
+        return (vec![], vec![]);
+    }
+    // Roots are found for the square-free part, which has the same roots but
+    // no repeated ones to slow down the numerical search.
+    let mut rest = div_rem(g, &gcd(g, &derivative(g))).unwrap().0;
+    let mut orders = vec![];
+    for root in durand_kerner(&rest) {
+        if let Some(ExactValue::RootOfUnity { order, .. }) = recognise_root(&root) {
+            if !orders.contains(&order) {
+                if let Some((quotient, remainder)) = div_rem(&rest, &cyclotomic(order)) {
+                    if remainder.is_empty() {
+                        rest = quotient;
+                        orders.push(order);
+                    }
+                }
+            }
+        }
+    }
+    orders.sort_unstable();
+    let roots_of_unity = orders.iter()
+        .flat_map(|&order| (0..order).filter(move |power| power.gcd(&order) == 1)
+            .map(move |power| ExactValue::RootOfUnity { order, power }))
+        .collect();
+    (roots_of_unity, durand_kerner(&rest))
+}
+
+// Integers ±1 count as the roots of unity they are.
+fn recognise_root(z: &Complex<f64>) -> Option<ExactValue> {
+    match recognise(z)? {
+        ExactValue::Integer(1) => Some(ExactValue::RootOfUnity { order: 1, power: 0 }),
+        ExactValue::Integer(-1) => Some(ExactValue::RootOfUnity { order: 2, power: 1 }),
+        ExactValue::Integer(_) => None,
+        root => Some(root)
+    }
+}
+
+// The Laurent polynomial multiplied by the power of q that makes it a
+// polynomial with non-vanishing constant term.
+fn numerator(p: &Laurent) -> Polynomial {
+    match (p.terms.keys().next(), p.terms.keys().next_back()) {
+        (Some(&low), Some(&high)) => {
+            let mut res = vec![BigInt::zero(); (high - low) as usize + 1];
+            for (exp, coef) in &p.terms {
+                res[(exp - low) as usize] = coef.clone();
+            }
+            res
+        }
+        _ => vec![]
+    }
+}
+
+fn trim(mut p: Polynomial) -> Polynomial {
+    while p.last().is_some_and(Zero::is_zero) {
+        p.pop();
+    }
+    p
+}
+
+fn derivative(p: &[BigInt]) -> Polynomial {
+    trim(p.iter().enumerate().skip(1).map(|(i, coef)| coef * BigInt::from(i)).collect())
+}
+
+// Divides by the greatest common divisor of the coefficients and makes the
+// leading coefficient positive.
+fn primitive(p: Polynomial) -> Polynomial {
+    let content = p.iter().fold(BigInt::zero(), |acc, coef| acc.gcd(coef));
+    if content.is_zero() {
+        return p;
+    }
+    let content = if p.last().unwrap().is_negative() { -content } else { content };
+    p.into_iter().map(|coef| coef / &content).collect()
+}
+
+// The quotient and remainder, provided that every leading coefficient met
+// along the way is divisible by that of the divisor.
+fn div_rem(dividend: &[BigInt], divisor: &[BigInt]) -> Option<(Polynomial, Polynomial)> {
+    let mut remainder = dividend.to_vec();
+    let degree = divisor.len() - 1;
+    if remainder.len() <= degree {
+        return Some((vec![], remainder));
+    }
+    let lead = divisor.last().unwrap();
+    let mut quotient = vec![BigInt::zero(); remainder.len() - degree];
+    for i in (0..quotient.len()).rev() {
+        let (coef, rest) = remainder[i + degree].div_rem(lead);
+        if !rest.is_zero() {
+            return None;
+        }
+        for (j, d) in divisor.iter().enumerate() {
+            remainder[i + j] -= &coef * d;
+        }
+        quotient[i] = coef;
+    }
+    remainder.truncate(degree);
+    Some((trim(quotient), trim(remainder)))
+}
+
+// The remainder of lc(b)^k a on division by b, which stays integral.
+fn pseudo_remainder(a: &[BigInt], b: &[BigInt]) -> Polynomial {
+    let mut remainder = a.to_vec();
+    let lead = b.last().unwrap();
+    while remainder.len() >= b.len() {
+        let coef = remainder.last().unwrap().clone();
+        let shift = remainder.len() - b.len();
+        for r in remainder.iter_mut() {
+            *r *= lead;
+        }
+        for (j, d) in b.iter().enumerate() {
+            remainder[shift + j] -= &coef * d;
+        }
+        remainder = trim(remainder);
+    }
+    remainder
+}
+
+// The primitive greatest common divisor, using that gcd(0, p) = p.
+fn gcd(a: &[BigInt], b: &[BigInt]) -> Polynomial {
+    let (mut a, mut b) = (primitive(a.to_vec()), primitive(b.to_vec()));
+    while !b.is_empty() {
+        let remainder = primitive(pseudo_remainder(&a, &b));
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+// All complex roots at once by the Durand–Kerner iteration, followed by a
+// Newton step against the polynomial itself.
+fn durand_kerner(p: &[BigInt]) -> Vec<Complex<f64>> {
+    if p.len() < 2 {
+        return vec![];
+    }
+    let lead = p.last().unwrap().to_f64().unwrap();
+    let monic: Vec<Complex<f64>> = p.iter().map(|coef| Complex::new(coef.to_f64().unwrap() / lead, 0.0)).collect();
+    let evaluate = |z: Complex<f64>| monic.iter().rev().fold(Complex::new(0.0, 0.0), |acc, coef| acc * z + coef);
+    let degree = monic.len() - 1;
+    let seed = Complex::new(0.4, 0.9);
+    let mut roots: Vec<Complex<f64>> = (0..degree).map(|k| seed.powi(k as i32)).collect();
+    for _ in 0..1000 {
+        let mut change: f64 = 0.0;
+        for k in 0..degree {
+            let denominator: Complex<f64> = (0..degree).filter(|&j| j != k).map(|j| roots[k] - roots[j]).product();
+            let step = evaluate(roots[k]) / denominator;
+            roots[k] -= step;
+            change = change.max(step.norm());
+        }
+        if change < 1e-14 {
+            break;
+        }
+    }
+    let slope = |z: Complex<f64>| monic.iter().enumerate().skip(1).rev()
+        .fold(Complex::new(0.0, 0.0), |acc, (i, coef)| acc * z + coef * i as f64);
+    roots.into_iter().map(|z| {
+        let s = slope(z);
+        if s.norm() > 1e-12 { z - evaluate(z) / s } else { z }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algebra::Metric;
+    use crate::scan;
+    use crate::word::parse_word;
+
+    fn polynomial(coefficients: &[i32]) -> Polynomial {
+        coefficients.iter().map(|coef| BigInt::from(*coef)).collect()
+    }
+
+    #[test]
+    fn gcds_of_polynomials_are_primitive() {
+        // (x − 1)(x + 2) and 2(x − 1)(x − 3).
+        let a = polynomial(&[-2, 1, 1]);
+        let b = polynomial(&[6, -8, 2]);
+        assert_eq!(gcd(&a, &b), polynomial(&[-1, 1]));
+        assert_eq!(gcd(&[], &b), polynomial(&[3, -4, 1]));
+        assert_eq!(gcd(&polynomial(&[1, 1]), &polynomial(&[-1, 1])), polynomial(&[1]));
+        assert_eq!(div_rem(&b, &polynomial(&[-1, 1])), Some((polynomial(&[-6, 2]), vec![])));
+        assert_eq!(div_rem(&a, &polynomial(&[1, 2])), None);
+    }
+
+    #[test]
+    fn roots_are_found_numerically() {
+        let mut roots = durand_kerner(&polynomial(&[-2, 0, 1]));
+        roots.sort_by(|a, b| a.re.partial_cmp(&b.re).unwrap());
+        assert!((roots[0] + 2f64.sqrt()).norm() < 1e-12);
+        assert!((roots[1] - 2f64.sqrt()).norm() < 1e-12);
+        assert!(durand_kerner(&polynomial(&[5])).is_empty());
+    }
+
+    #[test]
+    fn roots_are_split_into_roots_of_unity_and_others() {
+        // (q − 1)²(q² + q + 1)(q² − 2).
+        let g = polynomial(&[-2, 2, 1, 1, -2, -1, 1]);
+        let (roots_of_unity, mut others) = roots(&g);
+        assert_eq!(roots_of_unity, vec![
+            ExactValue::RootOfUnity { order: 1, power: 0 },
+            ExactValue::RootOfUnity { order: 3, power: 1 },
+            ExactValue::RootOfUnity { order: 3, power: 2 }
+        ]);
+        others.sort_by(|a, b| a.re.partial_cmp(&b.re).unwrap());
+        assert_eq!(others.len(), 2);
+        assert!((others[1] - 2f64.sqrt()).norm() < 1e-12);
+    }
+
+    #[test]
+    fn freely_trivial_words_hold_everywhere() {
+        let found = locus(&parse_word("NSEW").unwrap());
+        assert_eq!(found.polynomial, Laurent::default());
+        assert!(found.roots_of_unity.is_empty());
+    }
+
+    #[test]
+    fn relations_of_levels_are_found_exactly() {
+        let root = |order, power| ExactValue::RootOfUnity { order, power };
+        let found = locus(&parse_word("SWNE").unwrap());
+        assert!(found.roots_of_unity.contains(&root(2, 1)));
+
+        // E has eigenvalues −q⁻¹, 1 and −q, so E⁴ = I when q⁴ = 1, except at
+        // q = −1 where the eigenvalues coincide and E is not diagonalisable.
+        let found = locus(&parse_word("EEEE").unwrap());
+        assert_eq!(found.roots_of_unity, vec![root(1, 0), root(4, 1), root(4, 3)]);
+        assert!(found.other_roots.is_empty());
+
+        let found = locus(&parse_word(&"E".repeat(10)).unwrap());
+        assert!((1..5).all(|power| found.roots_of_unity.contains(&root(5, power))));
+        assert!(found.roots_of_unity.contains(&root(10, 3)));
+
+        // The solution of level 7 also works at every other root of unity
+        // whose order divides 30, apart from −1.
+        let found = locus(&parse_word(&"E".repeat(30)).unwrap());
+        assert!(found.roots_of_unity.contains(&root(15, 7)));
+        assert!(!found.roots_of_unity.contains(&root(2, 1)));
+    }
+
+    #[test]
+    fn words_are_relations_at_every_root() {
+        for word in ["SWNESWNE", "NESW", "NNEESSWW", "NNEE"] {
+            let word = parse_word(word).unwrap();
+            let found = locus(&word);
+            let distances = scan::distances(&word, &found.root_values(), Metric::Frobenius);
+            assert!(distances.iter().all(|distance| *distance < 1e-6), "{:?}", distances);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use num::{BigInt, Complex, One, Signed, Zero};
+
+use crate::exact::{self, ExactValue};
+use crate::expression::{to_subscript, to_superscript};
+
+// The ways in which values and matrices can be written: as plain text for the
+// game itself, or as LaTeX and MathML for papers, slides and web pages.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Notation {
+    Unicode,
+    Latex,
+    MathMl
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Symbol {
+    Q,
+    I,
+    // The root of unity exp(2πi/n).
+    Zeta(u32)
+}
+
+pub fn f64toa(x: &f64) -> String {
+    let mut buf = Vec::new();
+    dtoa::write(&mut buf, *x).unwrap();
+    std::str::from_utf8(&buf).unwrap().to_string()
+}
+
+// Writes a floating point number, leaving out the imaginary part unless
+// `imaginary` is set.
+pub fn complex(z: &Complex<f64>, imaginary: bool, notation: Notation) -> String {
+    let number = |x: f64| match notation {
+        Notation::MathMl => format!("<mn>{}</mn>", f64toa(&x)),
+        _ => f64toa(&x)
+    };
+    if !imaginary {
+        return number(z.re);
+    }
+    let i = power(Symbol::I, 1, notation);
+    format!("{}{}{}{}", number(z.re), sign(z.im < 0.0, false, notation), number(z.im.abs()), i)
+}
+
+pub fn power(symbol: Symbol, exp: i64, notation: Notation) -> String {
+    let base = match (symbol, notation) {
+        (Symbol::Q, Notation::Unicode) => "𝑞".to_owned(),
+        (Symbol::I, Notation::Unicode) => "𝑖".to_owned(),
+        (Symbol::Zeta(n), Notation::Unicode) => format!("ζ{}", to_subscript(&n.to_string())),
+        (Symbol::Q, Notation::Latex) => "q".to_owned(),
+        (Symbol::I, Notation::Latex) => "i".to_owned(),
+        (Symbol::Zeta(n), Notation::Latex) => format!("\\zeta_{{{}}}", n),
+        (Symbol::Q, Notation::MathMl) => "<mi>q</mi>".to_owned(),
+        (Symbol::I, Notation::MathMl) => "<mi>i</mi>".to_owned(),
+        (Symbol::Zeta(n), Notation::MathMl) => format!("<msub><mi>ζ</mi><mn>{}</mn></msub>", n)
+    };
+    match (exp, notation) {
+        (0, _) => String::new(),
+        (1, _) => base,
+        (_, Notation::Unicode) => format!("{}{}", base, to_superscript(&exp.to_string())),
+        (_, Notation::Latex) => format!("{}^{{{}}}", base, exp),
+        (_, Notation::MathMl) => format!("<msup>{}{}</msup>", base, integer(&BigInt::from(exp), notation))
+    }
+}
+
+fn integer(n: &BigInt, notation: Notation) -> String {
+    match notation {
+        Notation::MathMl if n.is_negative() => format!("<mrow><mo>−</mo><mn>{}</mn></mrow>", n.abs()),
+        Notation::MathMl => format!("<mn>{}</mn>", n),
+        _ => n.to_string()
+    }
+}
+
+fn sign(negative: bool, first: bool, notation: Notation) -> &'static str {
+    match (negative, first, notation) {
+        (false, true, _) => "",
+        (true, true, Notation::Unicode) => "−",
+        (true, false, Notation::Unicode) => " − ",
+        (true, true, Notation::Latex) => "-",
+        (true, false, Notation::Latex) => " - ",
+        (true, _, Notation::MathMl) => "<mo>−</mo>",
+        (false, false, Notation::MathMl) => "<mo>+</mo>",
+        (false, false, _) => " + "
+    }
+}
+
+// Writes a sum of integer multiples of powers of symbols, where terms without
+// a symbol are constant and terms with vanishing coefficients are left out.
+pub fn sum(terms: &[(BigInt, Option<(Symbol, i64)>)], notation: Notation) -> String {
+    let mut res = String::new();
+    for (coef, monomial) in terms.iter().filter(|(coef, _)| !coef.is_zero()) {
+        res.push_str(sign(coef.is_negative(), res.is_empty(), notation));
+        let magnitude = coef.abs();
+        let monomial = monomial.map(|(symbol, exp)| power(symbol, exp, notation)).unwrap_or_default();
+        if !magnitude.is_one() || monomial.is_empty() {
+            res.push_str(&integer(&magnitude, notation));
+        }
+        res.push_str(&monomial);
+    }
+    if res.is_empty() {
+        res = integer(&BigInt::zero(), notation);
+    }
+    wrap(res, notation)
+}
+
+pub fn fraction(numer: &BigInt, denom: &BigInt, notation: Notation) -> String {
+    let numer_sign = sign(numer.is_negative(), true, notation);
+    let numer = numer.abs();
+    match notation {
+        _ if denom.is_one() => wrap(format!("{}{}", numer_sign, integer(&numer, notation)), notation),
+        Notation::Unicode => format!("{}{}/{}", numer_sign, numer, denom),
+        Notation::Latex => format!("{}\\frac{{{}}}{{{}}}", numer_sign, numer, denom),
+        Notation::MathMl => format!("<mrow>{}<mfrac><mn>{}</mn><mn>{}</mn></mfrac></mrow>", numer_sign, numer, denom)
+    }
+}
+
+fn wrap(s: String, notation: Notation) -> String {
+    match notation {
+        Notation::MathMl => format!("<mrow>{}</mrow>", s),
+        _ => s
+    }
+}
+
+// Writes a value of q exactly if possible.
+pub fn value(q: &Complex<f64>, notation: Notation) -> String {
+    match exact::recognise(q) {
+        Some(q) => exact_value(q, notation),
+        None => wrap(complex(q, q.im != 0.0, notation), notation)
+    }
+}
+
+pub fn exact_value(q: ExactValue, notation: Notation) -> String {
+    match q {
+        ExactValue::Integer(n) => fraction(&BigInt::from(n), &BigInt::one(), notation),
+        ExactValue::RootOfUnity { order: 1, .. } => fraction(&BigInt::one(), &BigInt::one(), notation),
+        ExactValue::RootOfUnity { order: 2, .. } => fraction(&-BigInt::one(), &BigInt::one(), notation),
+        ExactValue::RootOfUnity { order: 4, power: p } => wrap(power(Symbol::I, p as i64, notation), notation),
+        ExactValue::RootOfUnity { order, power: p } => wrap(power(Symbol::Zeta(order), p as i64, notation), notation)
+    }
+}
+
+// Writes the nine entries of a matrix, given in row-major order.
+pub fn matrix(entries: &[String], notation: Notation) -> String {
+    let rows = entries.chunks(3);
+    match notation {
+        Notation::Unicode => rows.map(|row| row.join("  ")).collect::<Vec<_>>().join("\n"),
+        Notation::Latex => format!("\\begin{{pmatrix}} {} \\end{{pmatrix}}",
+            rows.map(|row| row.join(" & ")).collect::<Vec<_>>().join(" \\\\ ")),
+        Notation::MathMl => format!("<mrow><mo>(</mo><mtable>{}</mtable><mo>)</mo></mrow>",
+            rows.map(|row| format!("<mtr>{}</mtr>", row.iter().map(|entry| format!("<mtd>{}</mtd>", entry)).collect::<String>()))
+                .collect::<String>())
+    }
+}
+
+// Writes an equation `label = matrix`, as a block of its own for MathML.
+pub fn equation(label: &str, entries: &[String], notation: Notation) -> String {
+    match notation {
+        Notation::Unicode => format!("{} =\n{}", label, matrix(entries, notation)),
+        Notation::Latex => format!("{} = {}", label, matrix(entries, notation)),
+        Notation::MathMl => format!("<math display=\"block\">{}<mo>=</mo>{}</math>", label, matrix(entries, notation))
+    }
+}
+
+// Writes one matrix for each value of q, labelled by that value.
+pub fn labelled_matrices(qs: &[Complex<f64>], matrices: &[Vec<String>], notation: Notation) -> String {
+    qs.iter().zip(matrices).map(|(q, entries)| {
+        let q_label = format!("{}{}", power(Symbol::Q, 1, notation), match notation {
+            Notation::MathMl => "<mo>=</mo>",
+            _ => " = "
+        });
+        match notation {
+            Notation::Unicode => format!("{}{}:\n{}", q_label, value(q, notation), matrix(entries, notation)),
+            Notation::Latex => format!("{}{} \\colon {}", q_label, value(q, notation), matrix(entries, notation)),
+            Notation::MathMl => format!("<math display=\"block\">{}{}<mo>:</mo><mspace width=\"1em\"/>{}</math>",
+                q_label, value(q, notation), matrix(entries, notation))
+        }
+    }).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f64toa_works_small_number() {
+        let actual = f64toa(&2.0);
+        assert_eq!("2.0", actual);
+    }
+
+    #[test]
+    fn f64toa_works_large_number() {
+        let actual = f64toa(&2e25);
+        assert_eq!("2e25", actual);
+    }
+
+    #[test]
+    fn complex_numbers_have_negative_imaginary_parts_written_with_minus() {
+        assert_eq!(complex(&Complex::new(0.5, -1.0), true, Notation::Unicode), "0.5 − 1.0𝑖");
+        assert_eq!(complex(&Complex::new(0.5, 1.0), true, Notation::Unicode), "0.5 + 1.0𝑖");
+        assert_eq!(complex(&Complex::new(0.5, 1.0), false, Notation::Unicode), "0.5");
+    }
+
+    #[test]
+    fn sums_are_written_in_each_notation() {
+        let terms = [(BigInt::from(-1), Some((Symbol::Q, -1))), (BigInt::from(2), Some((Symbol::Zeta(5), 2))), (BigInt::from(3), None)];
+        assert_eq!(sum(&terms, Notation::Unicode), "−𝑞⁻¹ + 2ζ₅² + 3");
+        assert_eq!(sum(&terms, Notation::Latex), "-q^{-1} + 2\\zeta_{5}^{2} + 3");
+        assert_eq!(sum(&terms, Notation::MathMl),
+            "<mrow><mo>−</mo><msup><mi>q</mi><mrow><mo>−</mo><mn>1</mn></mrow></msup><mo>+</mo><mn>2</mn>\
+             <msup><msub><mi>ζ</mi><mn>5</mn></msub><mn>2</mn></msup><mo>+</mo><mn>3</mn></mrow>");
+        assert_eq!(sum(&[], Notation::Latex), "0");
+    }
+
+    #[test]
+    fn fractions_are_written_in_each_notation() {
+        let (numer, denom) = (BigInt::from(-5), BigInt::from(4));
+        assert_eq!(fraction(&numer, &denom, Notation::Unicode), "−5/4");
+        assert_eq!(fraction(&numer, &denom, Notation::Latex), "-\\frac{5}{4}");
+        assert_eq!(fraction(&numer, &denom, Notation::MathMl), "<mrow><mo>−</mo><mfrac><mn>5</mn><mn>4</mn></mfrac></mrow>");
+        assert_eq!(fraction(&numer, &BigInt::one(), Notation::Unicode), "−5");
+    }
+
+    #[test]
+    fn matrices_are_labelled_by_q() {
+        let entries: Vec<String> = (1..=9).map(|i| i.to_string()).collect();
+        let qs = [Complex::new(0.0, 1.0), Complex::new(2.5, 0.0)];
+        let latex = labelled_matrices(&qs, &[entries.clone(), entries], Notation::Latex);
+        assert_eq!(latex, "q = i \\colon \\begin{pmatrix} 1 & 2 & 3 \\\\ 4 & 5 & 6 \\\\ 7 & 8 & 9 \\end{pmatrix}\n\
+                           q = 2.5 \\colon \\begin{pmatrix} 1 & 2 & 3 \\\\ 4 & 5 & 6 \\\\ 7 & 8 & 9 \\end{pmatrix}");
+        assert_eq!(value(&Complex::from_polar(1.0, -2.0 * std::f64::consts::PI / 5.0), Notation::Latex), "\\zeta_{5}^{4}");
+        assert_eq!(exact_value(ExactValue::RootOfUnity { order: 2, power: 1 }, Notation::Unicode), "−1");
+        assert_eq!(exact_value(ExactValue::RootOfUnity { order: 4, power: 3 }, Notation::Unicode), "𝑖³");
+    }
+}
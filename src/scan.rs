@@ -0,0 +1,146 @@
+use num::{Complex, Zero};
+use wasm_bindgen::prelude::*;
+
+use crate::algebra::Metric;
+use crate::group::{Direction, Group};
+use crate::render::f64toa;
+
+// A rectangle of the complex plane sampled at `width` times `height` evenly
+// spaced points, including the corners, or the centre along a side with a
+// single point.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Grid {
+    pub re_min: f64,
+    pub re_max: f64,
+    pub im_min: f64,
+    pub im_max: f64,
+    pub width: usize,
+    pub height: usize
+}
+
+#[wasm_bindgen]
+impl Grid {
+    #[wasm_bindgen(constructor)]
+    pub fn new(re_min: f64, re_max: f64, im_min: f64, im_max: f64, width: usize, height: usize) -> Grid {
+        Grid { re_min, re_max, im_min, im_max, width, height }
+    }
+}
+
+impl Grid {
+    // The points of the grid row by row, starting with the top row so that
+    // the order matches that of an image.
+    pub fn points(&self) -> Vec<Complex<f64>> {
+        let step = |min: f64, max: f64, count: usize, i: usize| {
+            if count > 1 { min + (max - min) * i as f64 / (count - 1) as f64 } else { (min + max) / 2.0 }
+        };
+        (0..self.height).flat_map(|row| {
+            let im = step(self.im_max, self.im_min, self.height, row);
+            (0..self.width).map(move |column| Complex::new(step(self.re_min, self.re_max, self.width, column), im))
+        }).collect()
+    }
+}
+
+// The evenly spaced points from `from` to `to`, both included.
+pub fn segment(from: Complex<f64>, to: Complex<f64>, count: usize) -> Vec<Complex<f64>> {
+    (0..count).map(|i| if count > 1 { from + (to - from) * (i as f64 / (count - 1) as f64) } else { from }).collect()
+}
+
+// The distance of the product of the word from the identity at each value of
+// q.
+pub fn distances(word: &[Direction], qs: &[Complex<f64>], metric: Metric) -> Vec<f64> {
+    qs.iter().map(|q| {
+        let mut group = Group::new(q);
+        for direction in word {
+            group.push(direction);
+        }
+        group.current().distance(metric)
+    }).collect()
+}
+
+// The generators are undefined at q = 0, as they are for levels, so any such
+// point is left out rather than written with a meaningless distance. The first
+// line says so as a comment.
+pub fn to_csv(qs: &[Complex<f64>], distances: &[f64]) -> String {
+    let mut csv = "# Points with q = 0 or non-finite q are left out.\nq_re,q_im,distance\n".to_owned();
+    for (q, distance) in qs.iter().zip(distances).filter(|(q, _)| q.is_finite() && !q.is_zero()) {
+        csv.push_str(&[q.re, q.im, *distance].iter().map(f64toa).collect::<Vec<_>>().join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+// Writes the distances as a binary PPM image on a logarithmic scale, with
+// points where the word is a relation up to rounding in green and the largest
+// distances in the background colour of the game. An image needs every pixel,
+// so points where the distance is undefined, such as q = 0, are drawn like the
+// largest distances.
+pub fn to_ppm(distances: &[f64], width: usize, height: usize) -> Vec<u8> {
+    const FLOOR: f64 = 1e-12;
+    const NEAR: [f64; 3] = [119.0, 221.0, 119.0];
+    const FAR: [f64; 3] = [21.0, 21.0, 21.0];
+    let logs: Vec<f64> = distances.iter().map(|d| if d.is_nan() { f64::INFINITY } else { d.max(FLOOR).log10() }).collect();
+    let low = FLOOR.log10();
+    let high = logs.iter().copied().filter(|l| l.is_finite()).fold(low + 1.0, f64::max);
+    let mut ppm = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    for log in logs {
+        let t = ((log - low) / (high - low)).min(1.0);
+        ppm.extend(NEAR.iter().zip(&FAR).map(|(near, far)| (near + (far - near) * t).round() as u8));
+    }
+    ppm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::word::parse_word;
+
+    #[test]
+    fn grid_points_are_in_image_order() {
+        let points = Grid::new(-1.0, 1.0, 0.0, 2.0, 3, 2).points();
+        assert_eq!(points.len(), 6);
+        assert_eq!(points[0], Complex::new(-1.0, 2.0));
+        assert_eq!(points[2], Complex::new(1.0, 2.0));
+        assert_eq!(points[3], Complex::new(-1.0, 0.0));
+        assert_eq!(Grid::new(3.0, 4.0, 0.0, 1.0, 1, 1).points(), vec![Complex::new(3.5, 0.5)]);
+    }
+
+    #[test]
+    fn segments_include_end_points() {
+        let points = segment(Complex::new(2.0, 0.0), Complex::new(3.0, 1.0), 3);
+        assert_eq!(points, vec![Complex::new(2.0, 0.0), Complex::new(2.5, 0.5), Complex::new(3.0, 1.0)]);
+    }
+
+    #[test]
+    fn relations_vanish_where_they_hold() {
+        let word = parse_word("SWNE").unwrap();
+        let qs = [Complex::new(-1.0, 0.0), Complex::new(3.0, 0.0)];
+        let distances = distances(&word, &qs, Metric::Frobenius);
+        assert!(distances[0] < 1e-10);
+        assert!(distances[1] > 1.0);
+        let csv = to_csv(&qs, &distances);
+        assert_eq!(csv.lines().nth(1), Some("q_re,q_im,distance"));
+        assert!(csv.lines().nth(2).unwrap().starts_with("-1.0,0.0,"));
+        assert_eq!(csv.lines().count(), 4);
+    }
+
+    #[test]
+    fn zero_is_left_out_of_csv() {
+        let word = parse_word("SWNE").unwrap();
+        let qs = Grid::new(-1.0, 1.0, -1.0, 1.0, 3, 3).points();
+        assert_eq!(qs[4], Complex::new(0.0, 0.0));
+        let csv = to_csv(&qs, &distances(&word, &qs, Metric::Frobenius));
+        assert_eq!(csv.lines().count(), 2 + 8);
+        assert!(!csv.contains("NaN"));
+    }
+
+    #[test]
+    fn ppm_images_have_a_pixel_per_point() {
+        let ppm = to_ppm(&[0.0, 1e-3, 1e6, f64::NAN], 2, 2);
+        let header = b"P6\n2 2\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 12);
+        assert_eq!(&ppm[header.len()..header.len() + 3], &[119, 221, 119]);
+        assert_eq!(&ppm[ppm.len() - 6..], &[21, 21, 21, 21, 21, 21]);
+    }
+}
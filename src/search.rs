@@ -0,0 +1,104 @@
+use crate::algebra::Matrix;
+use crate::group::{generator_matrix, Direction};
+use crate::level::Goal;
+use crate::word;
+use num::Complex;
+
+const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
+
+// Finds a shortest non-empty reduced word whose matrices are the identity for
+// all of the given values of q, by iterative deepening over all reduced words
+// of length at most `max_length`.
+pub fn shortest_relation(qs: &[Complex<f64>], max_length: usize, tolerance: f64, goal: Goal) -> Option<Vec<Direction>> {
+    shortest_completion(qs, &[], max_length, tolerance, goal)
+}
+
+// Finds a shortest sequence of at most `max_length` moves taking the given word
+// to a solution. The moves may start by backtracking along the word, but may
+// not cancel it completely.
+pub fn shortest_completion(qs: &[Complex<f64>], word: &[Direction], max_length: usize, tolerance: f64, goal: Goal) -> Option<Vec<Direction>> {
+    let generators: Vec<[Matrix; 4]> = qs.iter()
+        .map(|q| DIRECTIONS.map(|direction| generator_matrix(&direction, q)))
+        .collect();
+    let products: Vec<Matrix> = generators.iter().map(|g| {
+        word.iter().fold(Matrix::identity(), |m, direction| &m * &g[index(direction)])
+    }).collect();
+    let mut search = Search { generators, word, moves: vec![], tolerance, goal };
+    (1..=max_length).find(|&length| search.extend(&products, length)).map(|_| search.moves)
+}
+
+fn index(direction: &Direction) -> usize {
+    DIRECTIONS.iter().position(|d| d == direction).unwrap()
+}
+
+struct Search<'a> {
+    generators: Vec<[Matrix; 4]>,
+    word: &'a [Direction],
+    moves: Vec<Direction>,
+    tolerance: f64,
+    goal: Goal
+}
+
+impl Search<'_> {
+    fn extend(&mut self, products: &[Matrix], remaining: usize) -> bool {
+        if remaining == 0 {
+            return products.iter().all(|m| self.goal.is_reached(m, self.tolerance)) &&
+                !word::free_reduce(&[self.word, &self.moves].concat()).is_empty();
+        }
+        for (i, direction) in DIRECTIONS.iter().enumerate() {
+            if self.moves.last() == Some(&direction.inverse()) {
+                continue;
+            }
+            let next: Vec<Matrix> = products.iter().zip(&self.generators).map(|(m, g)| m * &g[i]).collect();
+            self.moves.push(*direction);
+            if self.extend(&next, remaining - 1) {
+                return true;
+            }
+            self.moves.pop();
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::DEFAULT_TOLERANCE;
+    use crate::level_pack::parse_level_pack;
+
+    #[test]
+    fn shortest_relation_for_q_equal_to_one() {
+        let word = shortest_relation(&[Complex::new(1.0, 0.0)], 4, DEFAULT_TOLERANCE, Goal::Identity).unwrap();
+        assert_eq!(word, vec![Direction::North, Direction::North]);
+    }
+
+    #[test]
+    fn shortest_relation_respects_maximal_length() {
+        assert_eq!(shortest_relation(&[Complex::new(0.0, 1.0)], 3, DEFAULT_TOLERANCE, Goal::Identity), None);
+        assert_eq!(shortest_relation(&[Complex::new(2.0, 0.0)], 4, DEFAULT_TOLERANCE, Goal::Identity), None);
+    }
+
+    #[test]
+    fn shortest_completion_extends_word() {
+        use Direction::*;
+        let q = Complex::new(1.0, 0.0);
+        assert_eq!(shortest_completion(&[q], &[North], 4, DEFAULT_TOLERANCE, Goal::Identity), Some(vec![North]));
+        assert_eq!(shortest_completion(&[q], &[East, North], 4, DEFAULT_TOLERANCE, Goal::Identity), Some(vec![North, East]));
+        assert_eq!(shortest_completion(&[q], &[East, North], 1, DEFAULT_TOLERANCE, Goal::Identity), None);
+        let q = Complex::new(-1.0, 0.0);
+        assert_eq!(shortest_completion(&[q], &[South, West, North, East, East], 4, DEFAULT_TOLERANCE, Goal::Identity), Some(vec![West]));
+    }
+
+    #[test]
+    fn built_in_optimal_lengths_are_optimal() {
+        let levels = parse_level_pack(include_str!("levels.json")).unwrap();
+        for level in &levels {
+            let optimal_length = match level.optimal_length {
+                Some(optimal_length) => optimal_length,
+                None => continue
+            };
+            let word = shortest_relation(&level.qs, optimal_length, level.tolerance, level.goal).unwrap();
+            assert_eq!(word.len(), optimal_length);
+        }
+    }
+}
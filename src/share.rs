@@ -0,0 +1,134 @@
+use crate::group::Direction;
+
+const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// Packs a level index and a word into a URL safe string. The bytes are the
+// level index and the length of the word as LEB128 varints, followed by the
+// moves at 2 bits each and a Fletcher-16 checksum, all encoded as unpadded
+// base64url.
+pub fn encode(level: usize, word: &[Direction]) -> String {
+    let mut bytes = vec![];
+    write_varint(&mut bytes, level);
+    write_varint(&mut bytes, word.len());
+    for chunk in word.chunks(4) {
+        let byte = chunk.iter().enumerate()
+            .fold(0, |byte, (i, direction)| byte | (*direction as u8) << (2 * i));
+        bytes.push(byte);
+    }
+    let checksum = fletcher16(&bytes);
+    bytes.extend_from_slice(&checksum.to_be_bytes());
+    to_base64(&bytes)
+}
+
+pub fn decode(s: &str) -> Result<(usize, Vec<Direction>), String> {
+    let bytes = from_base64(s.trim().trim_start_matches('#'))?;
+    if bytes.len() < 2 {
+        return Err("The shared word is too short".to_owned());
+    }
+    let (bytes, checksum) = bytes.split_at(bytes.len() - 2);
+    if fletcher16(bytes).to_be_bytes() != checksum {
+        return Err("The shared word is corrupted".to_owned());
+    }
+    let mut rest = bytes;
+    let level = read_varint(&mut rest)?;
+    let length = read_varint(&mut rest)?;
+    if rest.len() != length.div_ceil(4) {
+        return Err("The shared word has the wrong length".to_owned());
+    }
+    let word = (0..length).map(|i| DIRECTIONS[((rest[i / 4] >> (2 * (i % 4))) & 3) as usize]).collect();
+    Ok((level, word))
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        bytes.push((n & 0x7f) as u8 | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<usize, String> {
+    let mut n: usize = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or("The shared word ended unexpectedly")?;
+        *bytes = rest;
+        n |= ((byte & 0x7f) as usize).checked_shl(shift).ok_or("The shared word is malformed")?;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err("The shared word is malformed".to_owned())
+}
+
+fn fletcher16(bytes: &[u8]) -> u16 {
+    let (mut a, mut b) = (0u16, 0u16);
+    for byte in bytes {
+        a = (a + *byte as u16) % 255;
+        b = (b + a) % 255;
+    }
+    (b << 8) | a
+}
+
+fn to_base64(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            s.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    s
+}
+
+fn from_base64(s: &str) -> Result<Vec<u8>, String> {
+    let values = s.bytes()
+        .map(|c| ALPHABET.iter().position(|a| *a == c).ok_or(format!("Invalid character '{}' in shared word", c as char)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut bytes = vec![];
+    for chunk in values.chunks(4) {
+        if chunk.len() == 1 {
+            return Err("The shared word has an invalid length".to_owned());
+        }
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, value)| n | (*value as u32) << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::word::parse_word;
+
+    #[test]
+    fn words_round_trip() {
+        for (level, word) in [(0, ""), (1, "SWNE"), (6, "EEEEEEEEEEEEEEEEEEEEEEEEEEEEEE"), (300, "NNESW")] {
+            let word = parse_word(word).unwrap();
+            let encoded = encode(level, &word);
+            assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+            assert_eq!(decode(&encoded), Ok((level, word)));
+        }
+    }
+
+    #[test]
+    fn encoding_is_compact() {
+        let word = parse_word(&"E".repeat(30)).unwrap();
+        assert_eq!(encode(6, &word).len(), 16);
+        assert_eq!(encode(1, &parse_word("SWNE").unwrap()), "AQSNmJI");
+    }
+
+    #[test]
+    fn corrupted_words_are_rejected() {
+        let encoded = encode(1, &parse_word("SWNE").unwrap());
+        let mut corrupted = encoded.clone().into_bytes();
+        corrupted[3] = if corrupted[3] == b'A' { b'B' } else { b'A' };
+        assert!(decode(std::str::from_utf8(&corrupted).unwrap()).is_err());
+        assert!(decode(&encoded[..encoded.len() - 1]).is_err());
+        assert!(decode("").is_err());
+        assert!(decode("A").is_err());
+        assert!(decode("AQ*d").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+// Version of the format written by `to_string`; bump it whenever the format
+// changes in a way older readers cannot handle.
+pub const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SavedGame {
+    pub version: u32,
+    pub active_level: usize,
+    pub levels: Vec<SavedLevel>,
+    #[serde(default)]
+    pub custom_levels: Vec<SavedCustomLevel>
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SavedLevel {
+    // Levels are matched by title, so that progress survives level packs
+    // being reordered or extended.
+    pub title: String,
+    pub word: String,
+    pub completed: bool,
+    pub best: Option<String>,
+    #[serde(default)]
+    pub best_found_at: Option<f64>,
+    #[serde(default)]
+    pub best_distances: Option<Vec<f64>>
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SavedCustomLevel {
+    pub qs: String,
+    pub description: String
+}
+
+#[derive(Deserialize)]
+struct Versioned {
+    version: u32
+}
+
+pub fn to_string(state: &SavedGame) -> String {
+    serde_json::to_string(state).expect("saved games can always be serialised")
+}
+
+pub fn from_str(s: &str) -> Result<SavedGame, String> {
+    let versioned: Versioned = serde_json::from_str(s).map_err(|e| e.to_string())?;
+    if versioned.version != VERSION {
+        return Err(format!("Unsupported version {} of saved game", versioned.version));
+    }
+    serde_json::from_str(s).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_games_round_trip() {
+        let state = SavedGame {
+            version: VERSION,
+            active_level: 2,
+            levels: vec![
+                SavedLevel {
+                    title: "Baby steps".to_owned(),
+                    word: "NE".to_owned(),
+                    completed: true,
+                    best: Some("NN".to_owned()),
+                    best_found_at: Some(1.6e12),
+                    best_distances: Some(vec![0.0])
+                },
+                SavedLevel {
+                    title: "Negative".to_owned(),
+                    word: "".to_owned(),
+                    completed: false,
+                    best: None,
+                    best_found_at: None,
+                    best_distances: None
+                }
+            ],
+            custom_levels: vec![SavedCustomLevel { qs: "2.9".to_owned(), description: "".to_owned() }]
+        };
+        assert_eq!(from_str(&to_string(&state)), Ok(state));
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() {
+        assert!(from_str(r#"{ "version": 0, "active_level": 0, "levels": [] }"#).is_err());
+        assert!(from_str(r#"{ "active_level": 0, "levels": [] }"#).is_err());
+        assert!(from_str(r#"{ "version": 2, "active_level": 0, "levels": [] }"#).is_err());
+        assert!(from_str(r#"{ "version": 1, "active_level": 0, "levels": [] }"#).is_ok());
+        assert!(from_str(r#"{ "version": 1, "active_level": 0,
+                              "levels": [{ "title": "", "word": "", "completed": true, "best": "NN" }] }"#).is_ok());
+        assert!(from_str(r#"{ "version": 1, "active_level": 0,
+                              "levels": [{ "word": "", "completed": true, "best": "NN" }] }"#).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::group::Direction;
+
+pub fn free_reduce(word: &[Direction]) -> Vec<Direction> {
+    let mut res: Vec<Direction> = vec![];
+    for direction in word {
+        if res.last() == Some(&direction.inverse()) {
+            res.pop();
+        } else {
+            res.push(*direction);
+        }
+    }
+    res
+}
+
+pub fn cyclic_reduce(word: &[Direction]) -> Vec<Direction> {
+    let reduced = free_reduce(word);
+    let mut start = 0;
+    let mut end = reduced.len();
+    while end - start > 1 && reduced[start] == reduced[end - 1].inverse() {
+        start += 1;
+        end -= 1;
+    }
+    reduced[start..end].to_vec()
+}
+
+// Two words are conjugate in the free group exactly when their cyclic
+// reductions are rotations of each other, so the least rotation of the cyclic
+// reduction is a canonical representative of the conjugacy class.
+pub fn canonical_form(word: &[Direction]) -> Vec<Direction> {
+    let reduced = cyclic_reduce(word);
+    (0..reduced.len().max(1))
+        .map(|i| [&reduced[i..], &reduced[..i]].concat())
+        .min()
+        .unwrap_or_default()
+}
+
+// Keeps the first word of each conjugacy class, in canonical form.
+pub fn distinct_up_to_conjugacy(words: &[Vec<Direction>]) -> Vec<Vec<Direction>> {
+    let mut res: Vec<Vec<Direction>> = vec![];
+    for word in words {
+        let canonical = canonical_form(word);
+        if !res.contains(&canonical) {
+            res.push(canonical);
+        }
+    }
+    res
+}
+
+pub fn word_to_string(word: &[Direction]) -> String {
+    word.iter().map(Direction::letter).collect()
+}
+
+pub fn parse_word(s: &str) -> Option<Vec<Direction>> {
+    s.chars().filter(|c| !c.is_whitespace()).map(Direction::from_letter).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Direction::*;
+
+    #[test]
+    fn free_reduce_cancels_inverse_pairs() {
+        assert_eq!(free_reduce(&[North, East, West, South]), vec![]);
+        assert_eq!(free_reduce(&[North, East, South, West]), vec![North, East, South, West]);
+        assert_eq!(free_reduce(&[West, North, South, South]), vec![West, South]);
+    }
+
+    #[test]
+    fn cyclic_reduce_strips_conjugating_letters() {
+        assert_eq!(cyclic_reduce(&[North, East, North, West, South]), vec![North]);
+        assert_eq!(cyclic_reduce(&[North, East, South]), vec![East]);
+        assert_eq!(cyclic_reduce(&[North, East, South, West]), vec![North, East, South, West]);
+        assert_eq!(cyclic_reduce(&[North, South]), vec![]);
+    }
+
+    #[test]
+    fn canonical_form_identifies_conjugates() {
+        let word = vec![East, South, West, North];
+        assert_eq!(canonical_form(&word), vec![North, East, South, West]);
+        assert_eq!(canonical_form(&[West, North, East, South]), canonical_form(&word));
+        assert_eq!(canonical_form(&[South, North, East, South, West, North]), canonical_form(&word));
+        assert_ne!(canonical_form(&[North, West, South, East]), canonical_form(&word));
+        assert_eq!(canonical_form(&[]), vec![]);
+    }
+
+    #[test]
+    fn distinct_up_to_conjugacy_removes_rotations() {
+        let words = vec![
+            vec![North, North],
+            vec![East, South, West, North],
+            vec![North, East, South, West],
+            vec![East, North, North, West]
+        ];
+        assert_eq!(distinct_up_to_conjugacy(&words), vec![vec![North, North], vec![North, East, South, West]]);
+    }
+
+    #[test]
+    fn words_round_trip_through_strings() {
+        let word = vec![North, East, South, West, West];
+        assert_eq!(word_to_string(&word), "NESWW");
+        assert_eq!(parse_word("NESWW"), Some(word.clone()));
+        assert_eq!(parse_word("nes ww"), Some(word));
+        assert_eq!(parse_word("NEX"), None);
+    }
+}
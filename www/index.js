@@ -1,26 +1,116 @@
-import { Game, Direction } from 'find-the-relation';
+import { Game, Direction, Goal, Metric } from 'find-the-relation';
 
 const $ = (elementId) => document.getElementById(elementId);
-const menuItems = document.getElementsByClassName('menu-item');
 
 const game = Game.new();
 
 let gameViewActive = false;
 
+function updateCompletedMarks() {
+  const completed = game.completed_levels();
+  for (let i = 0; i < completed.length; i += 1) {
+    $(`level-${i}-completed`).innerHTML = completed[i] ? '✅' : '';
+  }
+}
+
+function formatComplex(re, im) {
+  const round = (x) => Number(x.toFixed(5));
+  if (round(im) === 0) return `${round(re)}`;
+  return `${round(re)} ${im < 0 ? '−' : '+'} ${Math.abs(round(im))}𝑖`;
+}
+
+function showGeneratorMatrices() {
+  const directions = [
+    ['North', Direction.North],
+    ['South', Direction.South],
+    ['East', Direction.East],
+    ['West', Direction.West],
+  ];
+  for (const [name, direction] of directions) {
+    const header = document.createElement('h4');
+    header.textContent = name;
+    $('generator-matrices').appendChild(header);
+    const matrix = document.createElement('div');
+    matrix.innerHTML = game.generator_matrix_mathml(direction);
+    $('generator-matrices').appendChild(matrix);
+  }
+}
+
+const moveButtons = [
+  ['north-button', Direction.North],
+  ['south-button', Direction.South],
+  ['east-button', Direction.East],
+  ['west-button', Direction.West],
+];
+
+// Draws the logarithm of the distance from the identity after every prefix of
+// the word, one line per value of q.
+function drawHistory(qCount) {
+  const history = game.history();
+  const canvas = $('history');
+  const context = canvas.getContext('2d');
+  context.clearRect(0, 0, canvas.width, canvas.height);
+  const prefixes = history.length / (4 * qCount);
+  if (prefixes < 2) {
+    return;
+  }
+  const value = (p, i) => Math.log1p(history[4 * (p * qCount + i)]);
+  let max = 1e-9;
+  for (let p = 0; p < prefixes; p += 1) {
+    for (let i = 0; i < qCount; i += 1) {
+      max = Math.max(max, value(p, i));
+    }
+  }
+  const colours = ['#ffbb39', '#77dd77', '#77aadd', '#dd7777'];
+  for (let i = 0; i < qCount; i += 1) {
+    context.strokeStyle = colours[i % colours.length];
+    context.beginPath();
+    for (let p = 0; p < prefixes; p += 1) {
+      const x = (p / (prefixes - 1)) * (canvas.width - 2) + 1;
+      const y = canvas.height - 1 - (value(p, i) / max) * (canvas.height - 2);
+      if (p === 0) {
+        context.moveTo(x, y);
+      } else {
+        context.lineTo(x, y);
+      }
+    }
+    context.stroke();
+  }
+}
+
 function updateGameView() {
-  const entries = game.matrix_entries();
-  const isTrivial = game.matrix_is_identity();
+  window.localStorage.setItem('progress', game.save_state());
+  const symbolic = $('symbolic').checked;
+  const entries = symbolic ? game.matrix_polynomials() : game.matrix_entries();
+  const scalarGoal = game.goal() === Goal.Scalar;
+  const isTrivial = scalarGoal ? game.matrix_is_scalar() : game.matrix_is_identity();
   const qs = game.qs();
-  const distances = game.distance();
+  const distances = game.distances();
+  const traces = game.traces();
 
   $('completed').style.display = 'none';
+  $('hint').style.display = 'none';
+  $('scalar-solution').style.display = !scalarGoal && !game.is_solved() && game.is_solved_up_to_scalar() ? '' : 'none';
   $('description').textContent = game.level_description();
   const word = game.word();
   $('word').textContent = word;
+  const replayLength = game.replay_length();
+  $('replay-position').textContent = replayLength === undefined ? '' : `${game.replay_position()}/${replayLength}`;
+  const bestLength = game.best_length();
+  $('best-container').style.display = bestLength === undefined ? 'none' : '';
+  $('best').textContent = `${bestLength} moves (${game.best_word()})`;
+  for (const [button, direction] of moveButtons) {
+    const matrices = game.generator_matrices(direction);
+    const rows = (m) => [0, 1, 2].map((j) => m.slice(3 * j, 3 * j + 3).join('  ')).join('\n');
+    const evaluated = qs.map((q, i) => `𝑞 = ${q}:\n${rows(matrices[i + 1])}`);
+    $(button).title = [rows(matrices[0]), ...evaluated].join('\n\n');
+  }
   $('matrices').innerHTML = '';
-  for (let i = 0; i < entries.length / 9; i += 1) {
+  for (let i = 0; i < qs.length; i += 1) {
     const div = document.createElement('div');
-    const matrixHeader = document.createTextNode(`𝑞 = ${qs[i]}. Distance from target: ${distances[i]}`);
+    const distance = word === '' ? '∞' : distances[i].toFixed(5);
+    const trace = formatComplex(traces[2 * i], traces[2 * i + 1]);
+    const matrixHeader = document.createTextNode(`𝑞 = ${qs[i]}. Distance from target: ${distance}. Trace: ${trace}`);
     div.appendChild(matrixHeader);
     div.style.marginTop = '20px';
     div.style.padding = '10px';
@@ -33,22 +123,41 @@ function updateGameView() {
       for (let k = 0; k < 3; k += 1) {
         const td = tr.insertCell();
         td.style.width = '30em';
-        td.appendChild(document.createTextNode(entries[9 * i + 3 * j + k]));
+        const entry = symbolic ? entries[3 * j + k] : entries[9 * i + 3 * j + k];
+        td.appendChild(document.createTextNode(entry));
       }
     }
     $('matrices').appendChild(div);
   }
+  drawHistory(qs.length);
   if (game.is_solved()) {
     $('completed').style.display = '';
-    const currentLevel = game.active_level();
-    $(`level-${currentLevel}-completed`).innerHTML = '✅';
-    $('level-header').textContent = $(`level-${currentLevel}`).innerText;
+    const kernelElement = game.is_burau_kernel_element();
+    $('kernel-element').style.display = kernelElement && !scalarGoal ? '' : 'none';
+    $('central-element').style.display = kernelElement && scalarGoal ? '' : 'none';
+    // The roots are those of the identity relation, which a path that only
+    // reaches a multiple of the identity need not satisfy.
+    const roots = scalarGoal ? [] : Game.relation_roots(word);
+    $('relation-roots').style.display = roots.length === 0 ? 'none' : '';
+    $('relation-roots').textContent = `Your path takes you back to the identity matrix exactly when 𝑞 is one of ${roots.join(', ')}.`;
+    const rating = game.rating();
+    $('rating').style.display = rating === undefined ? 'none' : '';
+    $('rating').textContent = `${'★'.repeat(rating)}${'☆'.repeat(3 - rating)} (the shortest path has ${game.optimal_length()} moves)`;
+    // Words that were loaded or replayed solve the level without completing
+    // it, so only the completion marks kept by the game are shown.
+    const credited = game.is_credited();
+    $('completed-header').textContent = credited ? 'Level completed!' : 'Solved, but not by you';
+    $('not-credited').style.display = credited ? 'none' : '';
   }
+  updateCompletedMarks();
+  const currentLevel = game.active_level();
+  const mark = game.completed_levels()[currentLevel] ? ' ✅' : '';
+  $('level-header').textContent = `Level ${currentLevel + 1}: ${game.level_title()}${mark}`;
 }
 
 function navigate(i) {
   $('rules-menu-item').style.backgroundColor = '#252525';
-  for (let j = 0; j < menuItems.length - 1; j += 1) {
+  for (let j = 0; j < game.level_count(); j += 1) {
     $(`level-${j}`).style.backgroundColor = '#252525';
   }
   if (i === 0) {
@@ -61,20 +170,65 @@ function navigate(i) {
     $('landing').style.display = 'none';
     $('rules').style.display = 'none';
     $('game').style.display = '';
-    $('level-header').textContent = $(`level-${i - 1}`).innerText;
-    $(`level-${i - 1}`).style.backgroundColor = '#151515';
+    stopReplay();
     game.change_level(i - 1);
+    $(`level-${i - 1}`).style.backgroundColor = '#151515';
     gameViewActive = true;
     updateGameView();
   }
 }
 
-for (let i = 0; i < menuItems.length; i += 1) {
-  const j = i; // Copy to avoid capturing.
-  menuItems[i].onclick = () => navigate(j);
+$('rules-menu-item').onclick = () => navigate(0);
+
+const builtInLevelCount = game.level_count();
+
+// Every level, built-in or custom, gets its menu item from the level titles;
+// only custom levels can be removed.
+function updateLevelMenu() {
+  const titles = game.level_titles();
+  $('levels').innerHTML = '';
+  for (let i = 0; i < titles.length; i += 1) {
+    const item = document.createElement('div');
+    item.className = 'menu-item';
+    item.id = `level-${i}`;
+    item.appendChild(document.createTextNode(`Level ${i + 1}: ${titles[i]} `));
+    const completed = document.createElement('span');
+    completed.id = `level-${i}-completed`;
+    item.appendChild(completed);
+    if (i >= builtInLevelCount) {
+      const remove = document.createElement('span');
+      remove.textContent = ' ✖';
+      remove.onclick = (e) => {
+        e.stopPropagation();
+        game.remove_custom_level(i);
+        updateLevelMenu();
+        navigate(game.active_level() + 1);
+      };
+      item.appendChild(remove);
+    }
+    item.onclick = () => navigate(i + 1);
+    $('levels').appendChild(item);
+  }
+  updateCompletedMarks();
+}
+
+function addCustomLevel() {
+  let i;
+  try {
+    i = game.add_custom_level(
+      $('custom-qs').value,
+      'A level of your own making. Can you find a path to the identity for these values of 𝑞?',
+    );
+  } catch (e) {
+    alert(e);
+    return;
+  }
+  updateLevelMenu();
+  navigate(i + 1);
 }
 
 function move(direction) {
+  stopReplay();
   game.push(direction);
   updateGameView();
 }
@@ -84,13 +238,65 @@ const moveSouth = () => move(Direction.South);
 const moveEast = () => move(Direction.East);
 const moveWest = () => move(Direction.West);
 
+const directionNames = {
+  [Direction.North]: 'North',
+  [Direction.South]: 'South',
+  [Direction.East]: 'East',
+  [Direction.West]: 'West',
+};
+
+function showHint() {
+  const direction = game.hint();
+  $('hint').style.display = '';
+  $('hint').textContent = direction === undefined
+    ? 'No solution from here is known or short enough to find. Try resetting.'
+    : `Hint: try going ${directionNames[direction]}.`;
+}
+
+function share() {
+  window.location.hash = game.share();
+  if (navigator.clipboard) {
+    navigator.clipboard.writeText(window.location.href);
+  }
+}
+
+let replayTimer;
+
+function stopReplay() {
+  clearInterval(replayTimer);
+  replayTimer = undefined;
+}
+
+function loadReplay() {
+  stopReplay();
+  try {
+    game.load_word($('replay-word').value);
+  } catch (e) {
+    alert(e);
+    return;
+  }
+  updateGameView();
+}
+
+function stepReplay(forward) {
+  const moved = forward ? game.step_forward() : game.step_back();
+  if (!moved) stopReplay();
+  updateGameView();
+}
+
+function playReplay() {
+  stopReplay();
+  replayTimer = setInterval(() => stepReplay(true), 500);
+}
+
 function reset() {
+  stopReplay();
   game.reset();
   updateGameView();
 }
 
 function keydownHandler(e) {
-  if (!gameViewActive) return;
+  if (!gameViewActive || e.target.tagName === 'INPUT') return;
   const keyCode = e.which;
   if (keyCode === 38 || keyCode === 87) { // Up arrow and w
     moveNorth();
@@ -102,6 +308,8 @@ function keydownHandler(e) {
     moveWest();
   } else if (keyCode === 82) { // r
     reset();
+  } else if (keyCode === 72) { // h
+    showHint();
   }
 }
 
@@ -111,3 +319,35 @@ $('south-button').addEventListener('click', moveSouth);
 $('east-button').addEventListener('click', moveEast);
 $('west-button').addEventListener('click', moveWest);
 $('reset-button').addEventListener('click', reset);
+$('hint-button').addEventListener('click', showHint);
+$('share-button').addEventListener('click', share);
+$('replay-load-button').addEventListener('click', loadReplay);
+$('replay-back-button').addEventListener('click', () => { stopReplay(); stepReplay(false); });
+$('replay-forward-button').addEventListener('click', () => { stopReplay(); stepReplay(true); });
+$('replay-play-button').addEventListener('click', playReplay);
+$('symbolic').addEventListener('change', updateGameView);
+$('metric').addEventListener('change', () => {
+  game.set_metric(Metric[$('metric').value]);
+  updateGameView();
+});
+$('copy-latex-button').addEventListener('click', () => navigator.clipboard.writeText(game.matrices_latex()));
+$('custom-button').addEventListener('click', addCustomLevel);
+
+const savedProgress = window.localStorage.getItem('progress');
+if (savedProgress !== null) {
+  try {
+    game.load_state(savedProgress);
+  } catch (e) {
+    console.warn(`Could not restore saved progress: ${e}`);
+  }
+}
+showGeneratorMatrices();
+updateLevelMenu();
+if (window.location.hash.length > 1) {
+  try {
+    game.load_shared(window.location.hash.slice(1));
+    navigate(game.active_level() + 1);
+  } catch (e) {
+    console.warn(`Could not load shared word: ${e}`);
+  }
+}